@@ -3,8 +3,8 @@ use std::fs::File;
 use std::io::{BufRead, BufReader};
 use code_timing_macros::time_snippet;
 use const_format::concatcp;
-use regex::Regex;
 use adv_code_2024::*;
+use adv_code_2024::matching::AhoCorasick;
 
 const DAY: &str = "03";
 const INPUT_FILE: &str = concatcp!("input/", DAY, ".txt");
@@ -20,19 +20,11 @@ fn main() -> Result<()> {
     println!("=== Part 1 ===");
 
     fn part1<R: BufRead>(reader: R) -> Result<usize> {
-        let re = Regex::new(r"mul\((\d{1,3}),(\d{1,3})\)")?;
-        let lines = read_lines(reader);
-        let mut total = 0;
-
-        for line in lines {
-            for cap in re.captures_iter(&line) {
-                let first = cap.get(1).unwrap().as_str().parse::<i32>()?;
-                let second = cap.get(2).unwrap().as_str().parse::<i32>()?;
-                total += first * second;
-            }
-        }
+        let instruction_set = InstructionSet::with_standard_instructions();
+        let mut interpreter = Interpreter::new(instruction_set.select(&["mul"])?);
+        interpreter.run(reader)?;
 
-        Ok(total as usize)
+        Ok(usize::try_from(interpreter.total)?)
     }
 
     assert_eq!(161, part1(BufReader::new(TEST.as_bytes()))?);
@@ -46,27 +38,12 @@ fn main() -> Result<()> {
     println!("\n=== Part 2 ===");
     
     fn part2<R: BufRead>(reader: R) -> Result<usize> {
-        let re = Regex::new(r"(mul\((\d{1,3}),(\d{1,3})\)|do\(\)|don't\(\))")?;
-        let lines = read_lines(reader);
-        let mut total = 0;
-        let mut enabled = true;
-
-        for line in lines {
-            for cap in re.captures_iter(&line) {
-                let command = cap.get(1).unwrap().as_str();
-                if command.starts_with("mul") {
-                    if enabled {
-                        let first = cap.get(2).unwrap().as_str().parse::<i32>()?;
-                        let second = cap.get(3).unwrap().as_str().parse::<i32>()?;
-                        total += first * second;
-                    }
-                } else {
-                    enabled = !command.starts_with("don");
-                }
-            }
-        }
-        
-        Ok(total as usize)
+        let instruction_set = InstructionSet::with_standard_instructions();
+        let mut interpreter = Interpreter::new(instruction_set.select(&["mul", "do", "don't"])?);
+        interpreter.run(reader)?;
+        println!("Executed {} instructions", interpreter.trace.len());
+
+        Ok(usize::try_from(interpreter.total)?)
     }
     
     let test2 = "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
@@ -78,4 +55,218 @@ fn main() -> Result<()> {
     //endregion
 
     Ok(())
-}
\ No newline at end of file
+}
+
+type Value = i64;
+
+#[derive(Debug, Clone, Copy)]
+enum Effect {
+    /// Adds the result to the total while instructions are enabled
+    Compute(fn(&[Value]) -> Value),
+    Enable,
+    Disable,
+}
+
+/// An instruction written as `name(arg,...)` with exactly `arity` arguments of 1-3 digits.
+#[derive(Debug, Clone)]
+struct Instruction {
+    name: &'static str,
+    arity: usize,
+    effect: Effect,
+}
+
+impl Instruction {
+    fn new(name: &'static str, arity: usize, effect: Effect) -> Self {
+        Self { name, arity, effect }
+    }
+}
+
+struct InstructionSet {
+    instructions: Vec<Instruction>,
+}
+
+impl InstructionSet {
+    fn new() -> Self {
+        Self { instructions: Vec::new() }
+    }
+
+    fn with_standard_instructions() -> Self {
+        let mut set = Self::new();
+        set.register(Instruction::new("mul", 2, Effect::Compute(|args| args[0] * args[1])));
+        set.register(Instruction::new("do", 0, Effect::Enable));
+        set.register(Instruction::new("don't", 0, Effect::Disable));
+        set.register(Instruction::new("add", 2, Effect::Compute(|args| args[0] + args[1])));
+        set.register(Instruction::new("sub", 2, Effect::Compute(|args| args[0] - args[1])));
+        set
+    }
+
+    fn register(&mut self, instruction: Instruction) {
+        self.instructions.push(instruction);
+    }
+
+    fn select(&self, names: &[&str]) -> Result<Vec<&Instruction>> {
+        names
+            .iter()
+            .map(|name| {
+                self.instructions
+                    .iter()
+                    .find(|instruction| instruction.name == *name)
+                    .ok_or_else(|| anyhow!("unknown instruction {name}"))
+            })
+            .collect()
+    }
+}
+
+/// A well-formed instruction call found in the text.
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    /// Byte offset of the instruction name in the whole input
+    offset: usize,
+    instruction: usize,
+    args: Vec<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct TraceEntry {
+    offset: usize,
+    name: &'static str,
+    args: Vec<Value>,
+    result: Option<Value>,
+}
+
+/// Executes the instructions hidden in noisy text, line by line. The enable state
+/// and byte offsets carry over from one line to the next.
+struct Interpreter<'a> {
+    instructions: Vec<&'a Instruction>,
+    scanner: AhoCorasick,
+    enabled: bool,
+    offset: usize,
+    total: Value,
+    trace: Vec<TraceEntry>,
+}
+
+impl<'a> Interpreter<'a> {
+    fn new(instructions: Vec<&'a Instruction>) -> Self {
+        let scanner = AhoCorasick::new(instructions.iter().map(|i| format!("{}(", i.name)));
+        Self { instructions, scanner, enabled: true, offset: 0, total: 0, trace: Vec::new() }
+    }
+
+    fn tokenize<'t>(&'t self, line: &'t str) -> impl Iterator<Item = Token> + 't {
+        self.scanner
+            .find_overlapping(line.as_bytes())
+            .filter_map(move |m| {
+                let args = parse_args(&line[m.end..], self.instructions[m.pattern].arity)?;
+                Some(Token { offset: self.offset + m.start, instruction: m.pattern, args })
+            })
+    }
+
+    /// Runs the whole input. Lines are read with their terminators, so the offsets
+    /// also match inputs with `\r\n` line breaks.
+    fn run(&mut self, mut reader: impl BufRead) -> Result<()> {
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 0 {
+            self.run_line(&line);
+            line.clear();
+        }
+
+        Ok(())
+    }
+
+    /// Runs one line, including its line terminator if it has one.
+    fn run_line(&mut self, line: &str) {
+        let tokens = self.tokenize(line).collect::<Vec<_>>();
+        for token in tokens {
+            self.execute(token);
+        }
+        self.offset += line.len();
+    }
+
+    fn execute(&mut self, token: Token) {
+        let instruction = self.instructions[token.instruction];
+        let result = match instruction.effect {
+            Effect::Compute(_) if !self.enabled => return,
+            Effect::Compute(compute) => {
+                let value = compute(&token.args);
+                self.total += value;
+                Some(value)
+            }
+            Effect::Enable => {
+                self.enabled = true;
+                None
+            }
+            Effect::Disable => {
+                self.enabled = false;
+                None
+            }
+        };
+
+        self.trace.push(TraceEntry {
+            offset: token.offset,
+            name: instruction.name,
+            args: token.args,
+            result,
+        });
+    }
+}
+
+/// Parses the `arg,...)` that completes an instruction call with exactly `arity`
+/// numbers of 1-3 digits.
+fn parse_args(rest: &str, arity: usize) -> Option<Vec<Value>> {
+    let (args, _) = rest.split_once(')')?;
+    if arity == 0 {
+        return args.is_empty().then(Vec::new);
+    }
+
+    let args = args
+        .split(',')
+        .map(parse_short_number)
+        .collect::<Option<Vec<_>>>()?;
+
+    (args.len() == arity).then_some(args)
+}
+
+fn parse_short_number(s: &str) -> Option<Value> {
+    if (1..=3).contains(&s.len()) && s.bytes().all(|b| b.is_ascii_digit()) {
+        s.parse().ok()
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_across_lines() {
+        let instruction_set = InstructionSet::with_standard_instructions();
+        let instructions = instruction_set.select(&["mul", "do", "don't", "add", "sub"]).unwrap();
+        let mut interpreter = Interpreter::new(instructions);
+        interpreter.run_line("add(1,2)don't()mul(2,3)\n");
+        interpreter.run_line("sub(5,9)do()sub(5,9)add(1,2,3)mul(4)mul(2,2)");
+
+        assert_eq!(interpreter.total, 3 - 4 + 4);
+        let trace = interpreter.trace
+            .iter()
+            .map(|entry| (entry.offset, entry.name, entry.result))
+            .collect::<Vec<_>>();
+        assert_eq!(trace, [
+            (0, "add", Some(3)),
+            (8, "don't", None),
+            (32, "do", None),
+            (36, "sub", Some(-4)),
+            (60, "mul", Some(4)),
+        ]);
+    }
+
+    #[test]
+    fn test_trace_offsets_with_crlf() {
+        let instruction_set = InstructionSet::with_standard_instructions();
+        let mut interpreter = Interpreter::new(instruction_set.select(&["mul"]).unwrap());
+        interpreter.run("mul(1,2)\r\nxmul(3,4)\r\n\r\nmul(5,6)".as_bytes()).unwrap();
+
+        let offsets = interpreter.trace.iter().map(|entry| entry.offset).collect::<Vec<_>>();
+        assert_eq!(offsets, [0, 11, 23]);
+        assert_eq!(interpreter.total, 2 + 12 + 30);
+    }
+}
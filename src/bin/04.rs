@@ -1,12 +1,11 @@
 use adv_code_2024::*;
+use adv_code_2024::grid::Grid;
+use adv_code_2024::word_search::Stencil;
 use anyhow::*;
 use code_timing_macros::time_snippet;
 use const_format::concatcp;
-use regex::Regex;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use strum::IntoEnumIterator;
-use strum_macros::EnumIter;
 
 const DAY: &str = "04";
 const INPUT_FILE: &str = concatcp!("input/", DAY, ".txt");
@@ -24,6 +23,11 @@ MAMMMXMMMM
 MXMXAXMASX
 ";
 
+const X_MAS: &str = "\
+M.S
+.A.
+M.S";
+
 fn main() -> Result<()> {
     start_day(DAY);
 
@@ -32,16 +36,13 @@ fn main() -> Result<()> {
 
     fn part1<R: BufRead>(reader: R) -> Result<usize> {
         let puzzle = read_puzzle(reader)?;
-        let mut total = 0_usize;
-
-        for dir in Direction::iter() {
-            total += get_lines(&puzzle, &dir)
-                .into_iter()
-                .map(|line| count_xmas(&line))
-                .sum::<usize>();
+        let matches = puzzle.find_word("XMAS");
+        if is_small(&puzzle) {
+            let positions = matches.iter().flat_map(|m| m.positions()).collect::<Vec<_>>();
+            println!("{}", puzzle.highlight(&positions));
         }
 
-        Ok(total)
+        Ok(matches.len())
     }
 
     assert_eq!(18, part1(BufReader::new(TEST.as_bytes()))?);
@@ -56,31 +57,17 @@ fn main() -> Result<()> {
 
     fn part2<R: BufRead>(reader: R) -> Result<usize> {
         let puzzle = read_puzzle(reader)?;
-        let mut total = 0_usize;
-        let num_rows = puzzle.len();
-        let num_cols = puzzle[0].len();
-        let valid_neighbours = ["MMSS", "MSSM", "SMMS", "SSMM"];
-
-        for row in 1..num_rows - 1 {
-            for col in 1..num_cols - 1 {
-                let center = puzzle[row][col];
-                if center != 'A' {
-                    continue;
-                }
-
-                let mut neighbors = String::new();
-                neighbors.push(puzzle[row - 1][col - 1]);
-                neighbors.push(puzzle[row - 1][col + 1]);
-                neighbors.push(puzzle[row + 1][col + 1]);
-                neighbors.push(puzzle[row + 1][col - 1]);
-
-                if valid_neighbours.contains(&neighbors.as_str()) {
-                    total += 1
-                }
-            }
+        let matches = Stencil::parse(X_MAS)?
+            .rotations()
+            .iter()
+            .flat_map(|stencil| puzzle.find_stencil(stencil))
+            .collect::<Vec<_>>();
+        if is_small(&puzzle) {
+            let positions = matches.iter().flat_map(|m| m.positions.iter()).collect::<Vec<_>>();
+            println!("{}", puzzle.highlight(positions));
         }
 
-        Ok(total)
+        Ok(matches.len())
     }
 
     assert_eq!(9, part2(BufReader::new(TEST.as_bytes()))?);
@@ -93,118 +80,18 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-type Puzzle = Vec<Vec<char>>;
-
-#[derive(Debug, EnumIter)]
-enum Direction {
-    N,
-    S,
-    E,
-    W,
-    Ne,
-    Nw,
-    Se,
-    Sw,
-}
-
-fn count_xmas(line: &str) -> usize {
-    let re = Regex::new(r"XMAS").unwrap();
-    re.find_iter(line).count()
-}
-
-fn get_lines(puzzle: &Puzzle, dir: &Direction) -> Vec<String> {
-    get_start_positions(puzzle, dir)
-        .into_iter()
-        .map(|(r, c)| get_line(puzzle, r, c, dir))
-        .collect()
-}
-
-fn get_start_positions(puzzle: &Puzzle, dir: &Direction) -> Vec<(i32, i32)> {
-    let num_rows = puzzle.len() as i32;
-    let num_cols = puzzle[0].len() as i32;
-
-    match dir {
-        Direction::N => (0..num_cols)
-            .map(|c| (num_rows - 1, c))
-            .collect(),
-        Direction::S => (0..num_cols).map(|c| (0, c)).collect(),
-        Direction::E => (0..num_rows).map(|r| (r, 0)).collect(),
-        Direction::W => (0..num_rows)
-            .map(|r| (r, num_cols - 1))
-            .collect(),
-        Direction::Ne => {
-            let mut ret: Vec<(i32, i32)> = (0..num_rows).map(|r| (r, 0)).collect();
-            ret.extend((1..num_cols).map(|c| (num_rows - 1, c)));
-            ret
-        }
-        Direction::Nw => {
-            let mut ret: Vec<(i32, i32)> = (0..num_rows)
-                .map(|r| (r, num_cols - 1))
-                .collect();
-            ret.extend((0..num_cols - 1).map(|c| (num_rows - 1, c)));
-            ret
-        }
-        Direction::Se => {
-            let mut ret: Vec<(i32, i32)> = (0..num_rows).map(|r| (r, 0)).collect();
-            ret.extend((1..num_cols).map(|c| (0, c)));
-            ret
-        }
-        Direction::Sw => {
-            let mut ret: Vec<(i32, i32)> = (0..num_rows)
-                .map(|r| (r, num_cols - 1))
-                .collect();
-            ret.extend((0..num_cols - 1).map(|c| (0, c)));
-            ret
-        }
-    }
+fn is_small(puzzle: &Grid<char>) -> bool {
+    puzzle.num_rows <= 20 && puzzle.num_cols <= 20
 }
 
-fn get_delta(dir: &Direction) -> (i32, i32) {
-    match dir {
-        Direction::N => (-1, 0),
-        Direction::S => (1, 0),
-        Direction::E => (0, 1),
-        Direction::W => (0, -1),
-        Direction::Ne => (-1, 1),
-        Direction::Nw => (-1, -1),
-        Direction::Se => (1, 1),
-        Direction::Sw => (1, -1),
-    }
-}
-
-fn get_line(puzzle: &Puzzle, start_row: i32, start_col: i32, dir: &Direction) -> String {
-    let mut line = String::new();
-    let mut row = start_row;
-    let mut col = start_col;
-    let num_rows = puzzle.len() as i32;
-    let num_cols = puzzle[0].len() as i32;
-    let delta = get_delta(dir);
-
-    loop {
-        if !is_valid_cell(row, col, num_rows, num_cols) {
-            break;
-        }
-        line.push(puzzle[row as usize][col as usize]);
-        row += delta.0;
-        col += delta.1;
-    }
-
-    line
-}
-
-fn is_valid_cell(row: i32, col: i32, num_rows: i32, num_cols: i32) -> bool {
-    (0..num_rows).contains(&row) && (0..num_cols).contains(&col)
-}
-
-fn read_puzzle(reader: impl BufRead) -> Result<Puzzle> {
-    let mut ret = Vec::new();
+fn read_puzzle(reader: impl BufRead) -> Result<Grid<char>> {
+    let mut cells = Vec::new();
     for line in reader.lines() {
         let line = line?;
-        let mut row = Vec::new();
-        for c in line.chars() {
-            row.push(c);
-        }
-        ret.push(row);
+        cells.push(line.chars().collect::<Vec<_>>());
     }
-    Ok(ret)
+    let num_rows = cells.len() as i32;
+    let num_cols = cells.first().map_or(0, |row| row.len()) as i32;
+
+    Ok(Grid::new(num_rows, num_cols, cells))
 }
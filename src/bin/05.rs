@@ -1,9 +1,11 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
 use anyhow::*;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use code_timing_macros::time_snippet;
 use const_format::concatcp;
+use itertools::Itertools;
 use adv_code_2024::*;
 
 const DAY: &str = "05";
@@ -47,10 +49,10 @@ fn main() -> Result<()> {
     println!("=== Part 1 ===");
 
     fn part1<R: BufRead>(reader: R) -> Result<usize> {
-        let (rules, page_sequences) = read_rules_and_pages(reader);
+        let (rules, page_sequences) = read_rules_and_pages(reader)?;
         let mut total = 0;
         for page_seq in page_sequences {
-            if is_valid(&page_seq, &rules) {
+            if rules.check(&page_seq).is_none() {
                 total += page_seq[page_seq.len() / 2];
             }
         }
@@ -69,11 +71,13 @@ fn main() -> Result<()> {
     println!("\n=== Part 2 ===");
     
     fn part2<R: BufRead>(reader: R) -> Result<usize> {
-        let (rules, page_sequences) = read_rules_and_pages(reader);
+        let (rules, page_sequences) = read_rules_and_pages(reader)?;
         let mut total = 0;
         for page_seq in page_sequences {
-            if !is_valid(&page_seq, &rules) {
-                let sorted_pages = sort_pages(&page_seq, &rules.predecessors);
+            if rules.check(&page_seq).is_some() {
+                let sorted_pages = rules
+                    .order(&page_seq)
+                    .map_err(|cycle| anyhow!("cannot order {:?}: {}", page_seq, cycle))?;
                 total += sorted_pages[page_seq.len() / 2];
             }
         }
@@ -91,120 +95,200 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+type Page = i32;
+
 #[derive(Debug)]
 struct PageRules {
-    predecessors: HashMap<i32, HashSet<i32>>,
-    successors: HashMap<i32, HashSet<i32>>,
+    predecessors: HashMap<Page, HashSet<Page>>,
+    successors: HashMap<Page, HashSet<Page>>,
 }
 
-fn sort_pages(page_sequence: &[i32], predecessors: &HashMap<i32, HashSet<i32>>) -> Vec<i32> {
-    let mut ret = Vec::new();
-    let mut pages: HashSet<i32> = HashSet::from_iter(page_sequence.iter().cloned());
-    let mut relations = prune_relations(predecessors, page_sequence, false);
-    
-    while !relations.is_empty() {
-        let mut page_wo_pred: Option<i32> = None;
-        for page in &pages {
-            if !relations.contains_key(page) { 
-                page_wo_pred = Some(*page);
-                break;
-            }
-        }
-        if let Some(page) = page_wo_pred {
-            ret.push(page);
-            pages.remove(&page);
-            relations = prune_relations(&relations, &[page], true);
-        }
-    }
-    
-    if !pages.is_empty() {
-        ret.extend(pages);
-    }
-    
-    ret
+/// The rule `before|after` is broken because `after` is printed before `before`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Violation {
+    before: Page,
+    after: Page,
 }
 
-fn prune_relations(relations: &HashMap<i32, HashSet<i32>>, page_sequence: &[i32], exclude: bool) -> HashMap<i32, HashSet<i32>> {
-    let pages = HashSet::<i32>::from_iter(page_sequence.iter().cloned());
-    let mut ret = HashMap::new();
+/// Pages that must each come before the next one, the last one before the first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Cycle {
+    pages: Vec<Page>,
+}
 
-    for (key, values) in relations {
-        if !exclude && !pages.contains(key) || exclude && pages.contains(key) {
-            continue;
-        }
-        let mut pruned_values = HashSet::new();
-        for value in values {
-            if !exclude && pages.contains(value) || exclude && !pages.contains(value) {
-                pruned_values.insert(*value);  
-            }
-        }
-        if !pruned_values.is_empty() {
-            ret.insert(*key, pruned_values);    
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for page in &self.pages {
+            write!(f, "{page} -> ")?;
         }
+        write!(f, "{}", self.pages[0])
     }
-    
-    ret
 }
 
+impl PageRules {
+    fn successors_of(&self, page: Page) -> impl Iterator<Item = &Page> {
+        self.successors.get(&page).into_iter().flatten()
+    }
+
+    /// Returns the first rule broken by `update`, scanning the pages from left to right.
+    fn check(&self, update: &[Page]) -> Option<Violation> {
+        let positions = update
+            .iter()
+            .enumerate()
+            .map(|(idx, page)| (*page, idx))
+            .collect::<HashMap<_, _>>();
 
-fn is_valid(page_sequence: &Vec<i32>, rules: &PageRules) -> bool {
-    let mut predecessors: HashSet<i32> = HashSet::new();
-    let mut successors: HashSet<i32> = HashSet::new();
-    for page in &page_sequence[1..] {
-        successors.insert(*page);
+        update.iter().enumerate().find_map(|(idx, page)| {
+            self.successors_of(*page)
+                .filter_map(|succ| positions.get(succ).filter(|pos| **pos < idx).map(|pos| (*pos, *succ)))
+                .min()
+                .map(|(_, succ)| Violation { before: *page, after: succ })
+        })
     }
 
-    for page in page_sequence {
-        if let Some(expected_successors) = rules.successors.get(page) {
-            for pred in predecessors.iter() {
-                if expected_successors.contains(pred) {
-                    return false;
-                }
+    /// Sorts the pages of `update` with Kahn's algorithm on the rules between them.
+    /// Pages without constraints between them keep their relative order.
+    fn order(&self, update: &[Page]) -> Result<Vec<Page>, Cycle> {
+        let pages = update.iter().copied().collect::<HashSet<_>>();
+        let mut in_degree = update
+            .iter()
+            .map(|page| (*page, 0_usize))
+            .collect::<HashMap<_, _>>();
+        for page in update {
+            for succ in self.successors_of(*page).filter(|succ| pages.contains(succ)) {
+                *in_degree.get_mut(succ).unwrap() += 1;
             }
         }
-        if let Some(expected_predecessors) = rules.predecessors.get(page) {
-            for succ in successors.iter() {
-                if expected_predecessors.contains(succ) {
-                    return false;
+
+        let mut queue = update
+            .iter()
+            .copied()
+            .filter(|page| in_degree[page] == 0)
+            .collect::<VecDeque<_>>();
+        let mut ret = Vec::new();
+        while let Some(page) = queue.pop_front() {
+            ret.push(page);
+            let mut ready = Vec::new();
+            for succ in self.successors_of(page).filter(|succ| pages.contains(succ)) {
+                let degree = in_degree.get_mut(succ).unwrap();
+                *degree -= 1;
+                if *degree == 0 {
+                    ready.push(*succ);
                 }
             }
+            // Keep the order deterministic, the successors are stored in a set
+            ready.sort_by_key(|succ| update.iter().position(|p| p == succ));
+            queue.extend(ready);
+        }
+
+        if ret.len() == update.len() {
+            Result::Ok(ret)
+        } else {
+            let remaining = update
+                .iter()
+                .copied()
+                .filter(|page| in_degree[page] > 0)
+                .collect::<HashSet<_>>();
+            Err(self.find_cycle(&remaining))
         }
-        predecessors.insert(*page);
-        successors.remove(page);
     }
 
-    true
-}
+    /// Every page left over by Kahn's algorithm has a predecessor that is left over
+    /// as well, so walking backwards from any of them must run into a cycle.
+    fn find_cycle(&self, remaining: &HashSet<Page>) -> Cycle {
+        let mut path: Vec<Page> = Vec::new();
+        let mut current = *remaining.iter().min().unwrap();
 
-fn read_rules_and_pages(reader: impl BufRead) -> (PageRules, Vec<Vec<i32>>) {
-    let mut predecessors = HashMap::new();
-    let mut successors = HashMap::new();
-    let mut page_sequences = Vec::new();
-
-    let lines = read_lines(reader);
-    let mut parse_rules = true;
-    for line in lines {
-        if line.is_empty() {
-            parse_rules = false;
-            continue;
-        }
-        if parse_rules {
-            let pages: Vec<i32> = line.split("|")
-                .map(|s| { s.parse::<i32>().unwrap() })
-                .collect();
-            let predecessor = pages[0];
-            let successor = pages[1];
-            let mut entry: &mut HashSet<i32> = successors.entry(predecessor).or_default();
-            entry.insert(successor);
-            entry = predecessors.entry(successor).or_default();
-            entry.insert(predecessor);
-        } else {
-            let page_sequence: Vec<i32> = line.split(",")
-                .map(|s| { s.parse::<i32>().unwrap() })
-                .collect();
-            page_sequences.push(page_sequence);
+        loop {
+            if let Some(start) = path.iter().position(|page| *page == current) {
+                let mut pages = path[start..].to_vec();
+                pages.reverse();
+                // Start with the smallest page, so the same cycle is always reported alike
+                let smallest = pages.iter().position_min().unwrap();
+                pages.rotate_left(smallest);
+                return Cycle { pages };
+            }
+            path.push(current);
+            current = *self.predecessors[&current]
+                .iter()
+                .filter(|pred| remaining.contains(pred))
+                .min()
+                .unwrap();
         }
     }
+}
+
+fn read_rules_and_pages(reader: impl BufRead) -> Result<(PageRules, Vec<Vec<Page>>)> {
+    let mut predecessors: HashMap<Page, HashSet<Page>> = HashMap::new();
+    let mut successors: HashMap<Page, HashSet<Page>> = HashMap::new();
+    let mut sections = Sections::new(reader);
+    let rules = sections.expect_section("page ordering rules")?.transform(parse_rule)?;
+    let page_sequences = sections.expect_section("updates")?.transform(parse_update)?;
+    sections.expect_end()?;
 
-    (PageRules{predecessors, successors}, page_sequences)
-}
\ No newline at end of file
+    for (predecessor, successor) in rules {
+        successors.entry(predecessor).or_default().insert(successor);
+        predecessors.entry(successor).or_default().insert(predecessor);
+    }
+
+    Ok((PageRules { predecessors, successors }, page_sequences))
+}
+
+fn parse_rule(line: &str) -> Result<(Page, Page)> {
+    if line.contains(',') {
+        return Err(anyhow!("missing blank line between the rules and the updates"));
+    }
+    let (predecessor, successor) = line
+        .split_once('|')
+        .ok_or_else(|| anyhow!("expected a rule like 47|53, found {:?}", line))?;
+
+    Ok((parse_page(predecessor)?, parse_page(successor)?))
+}
+
+fn parse_update(line: &str) -> Result<Vec<Page>> {
+    let update = line.split(',').map(parse_page).collect::<Result<Vec<_>>>()?;
+    if let Some(page) = update.iter().duplicates().next() {
+        return Err(anyhow!("page {page} appears more than once in the update"));
+    }
+
+    Ok(update)
+}
+
+fn parse_page(page: &str) -> Result<Page> {
+    page.trim()
+        .parse()
+        .map_err(|_| anyhow!("invalid page number {:?}", page))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_and_order() {
+        let (rules, _) = read_rules_and_pages(BufReader::new(TEST.as_bytes())).unwrap();
+        assert_eq!(rules.check(&[75, 47, 61, 53, 29]), None);
+        assert_eq!(rules.check(&[97, 13, 75, 29, 47]), Some(Violation { before: 75, after: 13 }));
+        assert_eq!(rules.order(&[97, 13, 75, 29, 47]), Result::Ok(vec![97, 75, 47, 29, 13]));
+    }
+
+    #[test]
+    fn test_cycle() {
+        let input = "1|2\n2|3\n3|1\n3|4\n\n4,3,2,1\n";
+        let (rules, _) = read_rules_and_pages(BufReader::new(input.as_bytes())).unwrap();
+        let cycle = rules.order(&[4, 3, 2, 1]).unwrap_err();
+        assert_eq!(cycle.pages, [1, 2, 3]);
+        assert_eq!(cycle.to_string(), "1 -> 2 -> 3 -> 1");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |input: &str| format!("{:#}", read_rules_and_pages(BufReader::new(input.as_bytes())).unwrap_err());
+        assert_eq!(error("1|2\r\n2-3\r\n\r\n1,2,3\r\n"), "line 2: expected a rule like 47|53, found \"2-3\"");
+        assert_eq!(error("1|2\n\n1,x,3\n"), "line 3: invalid page number \"x\"");
+        assert_eq!(error("1|2\n2|3\n1,2,3\n"), "line 3: missing blank line between the rules and the updates");
+        assert_eq!(error("2|1\n\n1,2,1\n"), "line 3: page 1 appears more than once in the update");
+        assert_eq!(error("1|2\n2|3\n"), "line 3: missing updates, sections must be separated by a blank line");
+        assert!(read_rules_and_pages(BufReader::new("1|2 \r\n\r\n1,2,3 \r\n".as_bytes())).is_ok());
+    }
+}
@@ -1,10 +1,11 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use anyhow::*;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use code_timing_macros::time_snippet;
 use const_format::concatcp;
 use adv_code_2024::*;
+use adv_code_2024::grid::{Direction, Grid, Position};
 
 const DAY: &str = "06";
 const INPUT_FILE: &str = concatcp!("input/", DAY, ".txt");
@@ -30,8 +31,12 @@ fn main() -> Result<()> {
 
     fn part1<R: BufRead>(reader: R) -> Result<usize> {
         let area = Area::from_input(reader)?;
-        let (num_visited, _) = area.guard_walk(None);
-        Ok(num_visited)
+        let report = PatrolEngine::new(&area).patrol();
+        if report.outcome == Outcome::Loop {
+            return Err(anyhow!("Guard does not leave the area"));
+        }
+        println!("Guard leaves the area after {} steps", report.steps);
+        Ok(report.visited.len())
     }
 
     assert_eq!(41, part1(BufReader::new(TEST.as_bytes()))?);
@@ -46,27 +51,32 @@ fn main() -> Result<()> {
     
     fn part2<R: BufRead>(reader: R) -> Result<usize> {
         let area = Area::from_input(reader)?;
-        let mut total = 0;
-        for row in 0..area.height {
-            for col in 0..area.width {
-                if area.obstructions.contains(&(row, col)) {
-                    continue;
-                }
-                if area.guard == (row, col) {
-                    continue;
-                }
-                let mut obstructions_per_row = area.obstructions_per_row.clone();
-                let cols = obstructions_per_row.entry(row).or_default();
-                cols.push(col);
-                let mut obstructions_per_col = area.obstructions_per_col.clone();
-                let rows = obstructions_per_col.entry(col).or_default();
-                rows.push(row);
-
-                if is_loop(&area.guard, &area.guard_direction, &obstructions_per_row, &obstructions_per_col) {
-                    total += 1;
-                }
-            }
-        }
+        let engine = PatrolEngine::new(&area);
+        let report = engine.patrol();
+
+        // Only an obstruction on the original path can change the patrol. Every chunk
+        // of candidates works on its own copy of the engine.
+        let candidates = report.visited
+            .iter()
+            .filter(|pos| **pos != area.guard)
+            .collect::<Vec<_>>();
+        let chunks = candidates.chunks(OBSTRUCTIONS_PER_CHUNK).collect::<Vec<_>>();
+        let total = parallel::par_map_reduce(
+            &chunks,
+            |chunk| {
+                let mut engine = engine.clone();
+                chunk
+                    .iter()
+                    .filter(|pos| {
+                        engine.add_obstruction(pos);
+                        let is_loop = engine.ends_in_loop();
+                        engine.remove_obstruction(pos);
+                        is_loop
+                    })
+                    .count()
+            },
+            0,
+            |sum, count| sum + count);
         
         Ok(total)
     }
@@ -81,257 +91,248 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-type Position = (i32, i32);
-
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
-enum Direction {
-    North,
-    South,
-    East,
-    West,
-}
-
-impl Direction {
-    fn turn(&self) -> &Direction {
-        match self {
-            Direction::North => &Direction::East,
-            Direction::South => &Direction::West,
-            Direction::East => &Direction::South,
-            Direction::West => &Direction::North,
-        }
-    }
+const NUM_DIRECTIONS: usize = 4;
 
-    fn step(&self, pos: &Position) -> Position {
-        match self {
-            Direction::North => (pos.0 - 1, pos.1),
-            Direction::South => (pos.0 + 1, pos.1),
-            Direction::East => (pos.0, pos.1 + 1),
-            Direction::West => (pos.0, pos.1 - 1),
-        }
-    }
+/// Number of candidate obstructions checked with one copy of the [`PatrolEngine`]
+const OBSTRUCTIONS_PER_CHUNK: usize = 128;
 
-    fn step_back(&self, pos: &Position) -> Position {
-        match self {
-            Direction::North => (pos.0 + 1, pos.1),
-            Direction::South => (pos.0 - 1, pos.1),
-            Direction::East => (pos.0, pos.1 - 1),
-            Direction::West => (pos.0, pos.1 + 1),
-        }
-    }
+fn direction_index(direction: &Direction) -> usize {
+    direction.clone() as usize
 }
 
-
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Cell {
+    Empty,
+    Obstruction,
+}
 
 #[derive(Debug)]
 struct Area {
-    width: i32,
-    height: i32,
+    grid: Grid<Cell>,
     guard: Position,
     guard_direction: Direction,
-    obstructions: HashSet<Position>,
-    obstructions_per_row: HashMap<i32, Vec<i32>>,
-    obstructions_per_col: HashMap<i32, Vec<i32>>,
 }
 
 impl Area {
     fn from_input<R: BufRead>(reader: R) -> Result<Area> {
         let lines = read_lines(reader);
 
-        let height = lines.len() as i32;
-        let width = lines[0].len() as i32;
-        let mut guard: Option<Position> = None;
-        let mut guard_direction: Option<Direction> = None;
-        let mut obstructions: HashSet<Position> = HashSet::new();
-        let mut obstructions_per_row: HashMap<i32, Vec<i32>> = HashMap::new();
-        let mut obstructions_per_col: HashMap<i32, Vec<i32>> = HashMap::new();
+        let num_rows = lines.len() as i32;
+        let num_cols = lines[0].len() as i32;
+        let mut guard: Option<(Position, Direction)> = None;
+        let mut cells = Vec::new();
 
         for (row, line) in lines.iter().enumerate() {
+            let mut cell_row = Vec::new();
             for (col, ch) in line.chars().enumerate() {
                 match ch {
-                    '#' => {
-                        obstructions.insert((row as i32, col as i32));
-                        let mut entry = obstructions_per_row.entry(row as i32).or_default();
-                        entry.push(col as i32);
-                        entry = obstructions_per_col.entry(col as i32).or_default();
-                        entry.push(row as i32);
-                    }
-                    '<' | '>' | 'v' | '^' => {
-                        guard = Some((row as i32, col as i32));
-                        guard_direction = match ch {
+                    '#' => cell_row.push(Cell::Obstruction),
+                    _ => {
+                        cell_row.push(Cell::Empty);
+                        let direction = match ch {
                             '<' => Some(Direction::West),
                             '>' => Some(Direction::East),
                             'v' => Some(Direction::South),
                             '^' => Some(Direction::North),
                             _ => None,
+                        };
+                        if let Some(direction) = direction {
+                            guard = Some((Position::new(row as i32, col as i32), direction));
                         }
                     }
-                    _ => {}
                 }
             }
+            cells.push(cell_row);
         }
 
-        if let Some(guard) = guard {
-            Ok(Area{
-                height,
-                width,
-                guard,
-                guard_direction: guard_direction.unwrap(),
-                obstructions,
-                obstructions_per_row,
-                obstructions_per_col,
-            })
-        } else {
-            Err(anyhow!("Guard was not found"))
-        }
+        let (guard, guard_direction) = guard.ok_or_else(|| anyhow!("Guard was not found"))?;
+
+        Ok(Area {
+            grid: Grid::new(num_rows, num_cols, cells),
+            guard,
+            guard_direction,
+        })
     }
+}
 
-    fn guard_walk(&self, additional_obstruction: Option<Position>) -> (usize, bool) {
-        let mut visited = HashSet::new();
-        let mut visited_states = HashSet::new();
-        let mut curr_pos = self.guard;
-        let mut curr_dir = &self.guard_direction;
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Outcome {
+    Exit,
+    Loop,
+}
 
-        let mut obstructions = self.obstructions.clone();
-        if let Some(obstruction) = additional_obstruction {
-            obstructions.insert(obstruction);
-        }
+#[derive(Debug)]
+struct PatrolReport {
+    visited: HashSet<Position>,
+    outcome: Outcome,
+    steps: usize,
+}
 
-        loop {
-            if visited_states.contains(&(curr_pos, curr_dir)) {
-                return (visited.len(), true);
-            }
-            visited.insert(curr_pos);
-            visited_states.insert((curr_pos, curr_dir));
+/// Dense set of (position, direction) states.
+struct StateSet {
+    words: Vec<u64>,
+    num_cols: i32,
+}
 
-            let next_pos = curr_dir.step(&curr_pos);
-            if !(0..self.width).contains(&next_pos.1) || !(0..self.height).contains(&next_pos.0) {
-                return (visited.len(), false);
-            }
+impl StateSet {
+    fn new(num_rows: i32, num_cols: i32) -> Self {
+        let num_states = (num_rows * num_cols) as usize * NUM_DIRECTIONS;
+        Self { words: vec![0; num_states.div_ceil(64)], num_cols }
+    }
 
-            if !obstructions.contains(&next_pos) {
-                curr_pos = next_pos;
-            } else {
-                curr_dir = curr_dir.turn()
-            }
-        }
+    /// Adds the state and returns whether it was not contained before.
+    fn insert(&mut self, pos: &Position, direction: &Direction) -> bool {
+        let cell = (pos.row() * self.num_cols + pos.col()) as usize;
+        let state = cell * NUM_DIRECTIONS + direction_index(direction);
+        let mask = 1 << (state % 64);
+        let is_new = self.words[state / 64] & mask == 0;
+        self.words[state / 64] |= mask;
+        is_new
     }
 }
 
-fn is_loop(start_pos: &Position,
-           start_dir: &Direction,
-           obstacles_per_row: &HashMap<i32, Vec<i32>>,
-           obstacles_per_col: &HashMap<i32, Vec<i32>>) -> bool {
-
-    let mut state = (*start_pos, start_dir.clone());
-    let mut visited: HashSet<(Position, Direction)> = HashSet::new();
+/// Guard patrol engine. For every cell and direction the jump tables hold the cell
+/// where the guard stops in front of the next obstacle, so a patrol moves from turn
+/// to turn instead of from cell to cell.
+#[derive(Debug, Clone)]
+struct PatrolEngine {
+    grid: Grid<Cell>,
+    guard: Position,
+    guard_direction: Direction,
+    jumps: [Vec<Option<Position>>; NUM_DIRECTIONS],
+}
 
-    loop {
-        if visited.contains(&state) {
-            return true;
+impl PatrolEngine {
+    fn new(area: &Area) -> Self {
+        let num_cells = (area.grid.num_rows * area.grid.num_cols) as usize;
+
+        let mut engine = PatrolEngine {
+            grid: area.grid.clone(),
+            guard: area.guard.clone(),
+            guard_direction: area.guard_direction.clone(),
+            jumps: std::array::from_fn(|_| vec![None; num_cells]),
+        };
+        for row in 0..engine.grid.num_rows {
+            engine.update_row(row);
         }
-        visited.insert(state.clone());
-        let state_opt = next_state(&state.0,
-                                   &state.1,
-                                   obstacles_per_row,
-                                   obstacles_per_col);
-        if let Some(st) = state_opt {
-            state = st;
-        } else {
-            return false;
+        for col in 0..engine.grid.num_cols {
+            engine.update_col(col);
         }
+
+        engine
     }
-}
 
-fn next_state(pos: &Position,
-              dir: &Direction,
-              obstacles_per_row: &HashMap<i32, Vec<i32>>,
-              obstacles_per_col: &HashMap<i32, Vec<i32>>
-) -> Option<(Position, Direction)> {
-    let next_obstacle = get_next_obstacle(pos, dir, obstacles_per_row, obstacles_per_col);
-    next_obstacle.map(|pos| (dir.step_back(&pos), dir.turn().clone()))
-}
+    fn index(&self, pos: &Position) -> usize {
+        (pos.row() * self.grid.num_cols + pos.col()) as usize
+    }
 
-fn get_next_obstacle(pos: &Position,
-                     dir: &Direction,
-                     obstacles_per_row: &HashMap<i32, Vec<i32>>,
-                     obstacles_per_col: &HashMap<i32, Vec<i32>>
-) -> Option<Position> {
-    match dir {
-        Direction::North => {
-            let obstacle_rows = obstacles_per_col.get(&pos.1);
-            if let Some(obstacle_rows) = obstacle_rows {
-                let mut obstacle_rows = obstacle_rows.clone();
-                obstacle_rows.sort();
-                let mut row_opt: Option<i32> = None;
-                for row in obstacle_rows.iter() {
-                    if *row < pos.0 {
-                        row_opt = Some(*row);
-                    } else {
-                        break
-                    }
-                }
-                row_opt.map(|row| (row, pos.1))
-            } else {
-                None
+    fn add_obstruction(&mut self, pos: &Position) {
+        self.set_cell(pos, Cell::Obstruction);
+    }
+
+    fn remove_obstruction(&mut self, pos: &Position) {
+        self.set_cell(pos, Cell::Empty);
+    }
+
+    /// Only the jumps in the row and column of `pos` can change.
+    fn set_cell(&mut self, pos: &Position, cell: Cell) {
+        self.grid.set_value_at(pos, cell);
+        self.update_row(pos.row());
+        self.update_col(pos.col());
+    }
+
+    fn update_row(&mut self, row: i32) {
+        let num_cols = self.grid.num_cols;
+        self.update_line((0..num_cols).map(|col| Position::new(row, col)), Direction::West);
+        self.update_line((0..num_cols).rev().map(|col| Position::new(row, col)), Direction::East);
+    }
+
+    fn update_col(&mut self, col: i32) {
+        let num_rows = self.grid.num_rows;
+        self.update_line((0..num_rows).map(|row| Position::new(row, col)), Direction::North);
+        self.update_line((0..num_rows).rev().map(|row| Position::new(row, col)), Direction::South);
+    }
+
+    /// Recomputes the jumps in `direction` for a line of cells that is ordered
+    /// against `direction`, i.e. the guard walks towards the start of the line.
+    fn update_line(&mut self, line: impl Iterator<Item = Position>, direction: Direction) {
+        let dir_idx = direction_index(&direction);
+        let mut stop: Option<Position> = None;
+        let mut after_obstacle = false;
+        for pos in line {
+            let idx = self.index(&pos);
+            if self.grid.value_at(&pos) == Some(&Cell::Obstruction) {
+                self.jumps[dir_idx][idx] = None;
+                after_obstacle = true;
+                continue;
             }
-        }
-        Direction::South => {
-            let obstacle_rows = obstacles_per_col.get(&pos.1);
-            if let Some(obstacle_rows) = obstacle_rows {
-                let mut obstacle_rows = obstacle_rows.clone();
-                obstacle_rows.sort();
-                let mut row_opt: Option<i32> = None;
-                for row in obstacle_rows.iter().rev() {
-                    if *row > pos.0 {
-                        row_opt = Some(*row);
-                    } else {
-                        break
-                    }
-                }
-                row_opt.map(|row| (row, pos.1))
-            } else {
-                None
+            if after_obstacle {
+                stop = Some(pos);
+                after_obstacle = false;
             }
+            self.jumps[dir_idx][idx] = stop.clone();
         }
-        Direction::East => {
-            let obstacle_cols = obstacles_per_row.get(&pos.0);
-            if let Some(obstacle_cols) = obstacle_cols {
-                let mut obstacle_cols = obstacle_cols.clone();
-                obstacle_cols.sort();
-                let mut col_opt: Option<i32> = None;
-                for col in obstacle_cols.iter().rev() {
-                    if *col > pos.1 {
-                        col_opt = Some(*col);
-                    } else {
-                        break
-                    }
-                }
-                col_opt.map(|col| (pos.0, col))
-            } else {
-                None
-            }
+    }
+
+    fn distance(from: &Position, to: &Position) -> usize {
+        ((from.row() - to.row()).abs() + (from.col() - to.col()).abs()) as usize
+    }
+
+    /// Position where the guard leaves the area when walking from `pos` in `direction`.
+    fn last_inside(&self, pos: &Position, direction: &Direction) -> Position {
+        match direction {
+            Direction::North => Position::new(0, pos.col()),
+            Direction::South => Position::new(self.grid.num_rows - 1, pos.col()),
+            Direction::East => Position::new(pos.row(), self.grid.num_cols - 1),
+            Direction::West => Position::new(pos.row(), 0),
         }
-        Direction::West => {
-            let obstacle_cols = obstacles_per_row.get(&pos.0);
-            if let Some(obstacle_cols) = obstacle_cols {
-                let mut obstacle_cols = obstacle_cols.clone();
-                obstacle_cols.sort();
-                let mut col_opt: Option<i32> = None;
-                for col in obstacle_cols.iter() {
-                    if *col < pos.1 {
-                        col_opt = Some(*col);
-                    } else {
-                        break
-                    }
-                }
-                col_opt.map(|col| (pos.0, col))
-            } else {
-                None
+    }
+
+    fn patrol(&self) -> PatrolReport {
+        let mut visited = HashSet::new();
+        let (outcome, steps) = self.run(|from, to, direction| {
+            let mut pos = from.clone();
+            while pos != *to {
+                let next = pos.make_step(direction);
+                visited.insert(pos);
+                pos = next;
             }
-        }
+            visited.insert(pos);
+        });
+
+        PatrolReport { visited, outcome, steps }
     }
-}
 
+    fn ends_in_loop(&self) -> bool {
+        self.run(|_, _, _| {}).0 == Outcome::Loop
+    }
 
+    /// Runs the patrol and calls `on_segment` for every straight walk of the guard.
+    fn run(&self, mut on_segment: impl FnMut(&Position, &Position, &Direction)) -> (Outcome, usize) {
+        let mut seen_states = StateSet::new(self.grid.num_rows, self.grid.num_cols);
+        let mut pos = self.guard.clone();
+        let mut direction = self.guard_direction.clone();
+        let mut steps = 0;
 
+        loop {
+            if !seen_states.insert(&pos, &direction) {
+                return (Outcome::Loop, steps);
+            }
 
+            match &self.jumps[direction_index(&direction)][self.index(&pos)] {
+                Some(stop) => {
+                    on_segment(&pos, stop, &direction);
+                    steps += Self::distance(&pos, stop);
+                    pos = stop.clone();
+                    direction = direction.turn_right();
+                }
+                None => {
+                    let exit = self.last_inside(&pos, &direction);
+                    on_segment(&pos, &exit, &direction);
+                    steps += Self::distance(&pos, &exit);
+                    return (Outcome::Exit, steps);
+                }
+            }
+        }
+    }
+}
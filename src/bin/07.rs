@@ -1,12 +1,10 @@
 use anyhow::*;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::str::FromStr;
 use code_timing_macros::time_snippet;
 use const_format::concatcp;
-use strum_macros::EnumIter;
 use adv_code_2024::*;
-use itertools::Itertools;
-use strum::IntoEnumIterator;
 
 const DAY: &str = "07"; 
 const INPUT_FILE: &str = concatcp!("input/", DAY, ".txt");
@@ -30,12 +28,8 @@ fn main() -> Result<()> {
     println!("=== Part 1 ===");
 
     fn part1<R: BufRead>(reader: R) -> Result<usize> {
-        let equations = read_equations(reader)?;
-
-        let result: i64 = equations.iter()
-            .filter(|eq| eq.is_valid() )
-            .map(|eq| eq.lhs)
-            .sum();
+        let equations = read_equations::<i64>(reader)?;
+        let result = sum_valid_equations(&equations, &["+", "*"])?;
 
         Ok(result as usize)
     }
@@ -51,14 +45,10 @@ fn main() -> Result<()> {
     println!("\n=== Part 2 ===");
     
     fn part2<R: BufRead>(reader: R) -> Result<usize> {
-        let equations = read_equations(reader)?;
-
-        let result: i64 = equations.iter()
-            .filter(|eq| eq.is_valid2() )
-            .map(|eq| eq.lhs)
-            .sum();
+        let equations = read_equations::<u128>(reader)?;
+        let result = sum_valid_equations(&equations, &["+", "*", "||"])?;
 
-        Ok(result as usize)         
+        Ok(usize::try_from(result)?)
     }
     
     assert_eq!(11387, part2(BufReader::new(TEST.as_bytes()))?);
@@ -71,124 +61,309 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-#[derive(Debug, EnumIter, Clone)]
-enum Operator {
-    Add,
-    Multiply,
+trait Number: Copy + Ord + FromStr + Send + Sync {
+    const ZERO: Self;
+    const TEN: Self;
+
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+    fn checked_div_exact(self, rhs: Self) -> Option<Self>;
+    fn checked_rem(self, rhs: Self) -> Option<Self>;
+
+    /// Smallest power of ten that is greater than `self`, i.e. the factor
+    /// by which a number has to be shifted to append `self` to it.
+    fn digit_factor(self) -> Option<Self> {
+        let mut factor = Self::TEN;
+        while factor <= self {
+            factor = factor.checked_mul(Self::TEN)?;
+        }
+        Some(factor)
+    }
+}
+
+macro_rules! impl_number {
+    ($($t:ty),*) => {
+        $(
+            impl Number for $t {
+                const ZERO: Self = 0;
+                const TEN: Self = 10;
+
+                fn checked_add(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_add(self, rhs)
+                }
+
+                fn checked_sub(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_sub(self, rhs)
+                }
+
+                fn checked_mul(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_mul(self, rhs)
+                }
+
+                fn checked_div_exact(self, rhs: Self) -> Option<Self> {
+                    match <$t>::checked_rem(self, rhs) {
+                        Some(0) => <$t>::checked_div(self, rhs),
+                        _ => None,
+                    }
+                }
+
+                fn checked_rem(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_rem(self, rhs)
+                }
+            }
+        )*
+    };
 }
 
-impl Operator {
-    fn cartesian_product(n: usize) -> Vec<Vec<Operator>> {
-        let operators = Operator::iter().collect_vec();
-        cartesian_product(n, &operators)
+impl_number!(i64, u128);
+
+trait Operator<N: Number>: Sync + Send {
+    fn symbol(&self) -> &'static str;
+
+    /// Returns `lhs <op> rhs` or `None` if the result overflows.
+    fn apply(&self, lhs: N, rhs: N) -> Option<N>;
+
+    /// Returns the non-negative left operand `x` for which `x <op> operand == result`,
+    /// if there is one.
+    fn inverse(&self, result: N, operand: N) -> Option<N>;
+
+    /// Whether `x <op> operand == result` holds for every `x`, like `x * 0 == 0`.
+    fn absorbs(&self, _result: N, _operand: N) -> bool {
+        false
     }
 }
 
-#[derive(Debug, EnumIter, Clone)]
-enum Operator2 {
-    Add,
-    Multiply,
-    Concatenation,
+struct Add;
+
+impl<N: Number> Operator<N> for Add {
+    fn symbol(&self) -> &'static str {
+        "+"
+    }
+
+    fn apply(&self, lhs: N, rhs: N) -> Option<N> {
+        lhs.checked_add(rhs)
+    }
+
+    fn inverse(&self, result: N, operand: N) -> Option<N> {
+        result.checked_sub(operand).filter(|x| *x >= N::ZERO)
+    }
 }
 
-impl Operator2 {
-    fn cartesian_product(n: usize) -> Vec<Vec<Operator2>> {
-        let operators = Operator2::iter().collect_vec();
-        cartesian_product(n, &operators)
+struct Multiply;
+
+impl<N: Number> Operator<N> for Multiply {
+    fn symbol(&self) -> &'static str {
+        "*"
+    }
+
+    fn apply(&self, lhs: N, rhs: N) -> Option<N> {
+        lhs.checked_mul(rhs)
+    }
+
+    fn inverse(&self, result: N, operand: N) -> Option<N> {
+        result.checked_div_exact(operand)
+    }
+
+    fn absorbs(&self, result: N, operand: N) -> bool {
+        operand == N::ZERO && result == N::ZERO
     }
 }
 
-fn cartesian_product<T: Clone>(n: usize, operators: &Vec<T>) -> Vec<Vec<T>> {
-    let operators_list = vec![operators.clone(); n];
-    let multi_product = operators_list.iter().multi_cartesian_product();
-    let mut ret = Vec::new();
-    for item in multi_product {
-        let mut ops = vec![];
-        for op in item {
-            ops.push(op.clone());
+struct Concatenation;
+
+impl<N: Number> Operator<N> for Concatenation {
+    fn symbol(&self) -> &'static str {
+        "||"
+    }
+
+    fn apply(&self, lhs: N, rhs: N) -> Option<N> {
+        lhs.checked_mul(rhs.digit_factor()?)?.checked_add(rhs)
+    }
+
+    fn inverse(&self, result: N, operand: N) -> Option<N> {
+        let factor = operand.digit_factor()?;
+        if result.checked_rem(factor)? == operand {
+            result.checked_sub(operand)?.checked_div_exact(factor)
+        } else {
+            None
         }
-        ret.push(ops);
     }
-    ret
+}
+
+struct OperatorRegistry<N: Number> {
+    operators: Vec<Box<dyn Operator<N>>>,
+}
+
+impl<N: Number> OperatorRegistry<N> {
+    fn new() -> Self {
+        Self { operators: Vec::new() }
+    }
+
+    fn with_standard_operators() -> Self {
+        let mut registry = Self::new();
+        registry.register(Box::new(Add));
+        registry.register(Box::new(Multiply));
+        registry.register(Box::new(Concatenation));
+        registry
+    }
+
+    fn register(&mut self, operator: Box<dyn Operator<N>>) {
+        self.operators.push(operator);
+    }
+
+    fn select(&self, symbols: &[&str]) -> Result<Vec<&dyn Operator<N>>> {
+        symbols
+            .iter()
+            .map(|symbol| {
+                self.operators
+                    .iter()
+                    .find(|op| op.symbol() == *symbol)
+                    .map(|op| op.as_ref())
+                    .ok_or_else(|| anyhow!("unknown operator {symbol}"))
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone)]
-struct Equation {
-    lhs: i64,
-    operands: Vec<i64>,
+struct Equation<N: Number> {
+    lhs: N,
+    operands: Vec<N>,
 }
 
-impl Equation {
+impl<N: Number> Equation<N> {
 
-    fn is_valid(&self) -> bool {
-        let n = self.operands.len() - 1;
-        let operator_products = Operator::cartesian_product(n);
-        for operator_product in operator_products {
-            if self.lhs == self.evaluate_rhs(&operator_product) {
-                return true;
-            }
-        }
-        false
+    fn is_valid(&self, operators: &[&dyn Operator<N>]) -> bool {
+        self.solve(operators)
+            .is_some_and(|assignment| self.evaluate_rhs(&assignment) == Some(self.lhs))
     }
 
-    fn evaluate_rhs(&self, operators: &[Operator]) -> i64 {
-        let mut ret: i64 = self.operands[0];
+    /// Evaluates the right hand side from left to right. Returns `None` on overflow.
+    fn evaluate_rhs(&self, operators: &[&dyn Operator<N>]) -> Option<N> {
+        self.operands[1..]
+            .iter()
+            .zip(operators)
+            .try_fold(self.operands[0], |acc, (operand, op)| op.apply(acc, *operand))
+    }
 
-        for (i, operand) in self.operands.iter().skip(1).enumerate() {
-            let op = &operators[i];
-            match *op {
-                Operator::Add => { ret += operand }
-                Operator::Multiply => { ret *= operand }
-            }
+    /// Searches for operators that make the equation true. The search starts with the
+    /// last operand and undoes one operator per step, so dead ends are pruned early.
+    fn solve<'a>(&self, operators: &[&'a dyn Operator<N>]) -> Option<Vec<&'a dyn Operator<N>>> {
+        let mut assignment = Vec::new();
+        if self.solve_backwards(self.operands.len() - 1, self.lhs, operators, &mut assignment) {
+            assignment.reverse();
+            Some(assignment)
+        } else {
+            None
         }
-
-        ret
     }
 
-    fn is_valid2(&self) -> bool {
-        let n = self.operands.len() - 1;
-        let operator_products = Operator2::cartesian_product(n);
-        for operator_product in operator_products {
-            if self.lhs == self.evaluate_rhs2(&operator_product) {
-                return true;
+    fn solve_backwards<'a>(&self,
+                           idx: usize,
+                           target: N,
+                           operators: &[&'a dyn Operator<N>],
+                           assignment: &mut Vec<&'a dyn Operator<N>>) -> bool {
+        if idx == 0 {
+            return target == self.operands[0];
+        }
+
+        let operand = self.operands[idx];
+        for op in operators {
+            if op.absorbs(target, operand) {
+                // Any value of the operands before works, as long as it can be computed
+                let mut prefix = Vec::new();
+                if self.evaluate_any_prefix(0, self.operands[0], idx - 1, operators, &mut prefix) {
+                    assignment.push(*op);
+                    assignment.extend(prefix.into_iter().rev());
+                    return true;
+                }
+            } else if let Some(prev_target) = op.inverse(target, operand) {
+                assignment.push(*op);
+                if self.solve_backwards(idx - 1, prev_target, operators, assignment) {
+                    return true;
+                }
+                assignment.pop();
             }
         }
+
         false
     }
 
-    fn evaluate_rhs2(&self, operators: &[Operator2]) -> i64 {
-        let mut ret: i64 = self.operands[0];
+    /// Searches for operators, in evaluation order, with which `operands[..=last]`
+    /// can be evaluated without overflow.
+    fn evaluate_any_prefix<'a>(&self,
+                               idx: usize,
+                               acc: N,
+                               last: usize,
+                               operators: &[&'a dyn Operator<N>],
+                               prefix: &mut Vec<&'a dyn Operator<N>>) -> bool {
+        if idx == last {
+            return true;
+        }
 
-        for (i, operand) in self.operands.iter().skip(1).enumerate() {
-            let op = &operators[i];
-            match *op {
-                Operator2::Add => { ret += operand }
-                Operator2::Multiply => { ret *= operand }
-                Operator2::Concatenation => {
-                    let a = ret.to_string();
-                    let b = operand.to_string();
-                    let ab = a + b.as_str();
-                    ret = ab.parse::<i64>().unwrap();
+        for op in operators {
+            if let Some(next) = op.apply(acc, self.operands[idx + 1]) {
+                prefix.push(*op);
+                if self.evaluate_any_prefix(idx + 1, next, last, operators, prefix) {
+                    return true;
                 }
+                prefix.pop();
             }
         }
 
-        ret
+        false
     }
 }
 
-fn read_equations<R: BufRead>(reader: R) -> Result<Vec<Equation>> {
+fn sum_valid_equations<N: Number>(equations: &[Equation<N>], symbols: &[&str]) -> Result<N> {
+    let registry = OperatorRegistry::with_standard_operators();
+    let operators = registry.select(symbols)?;
+
+    parallel::par_map_reduce(
+        equations,
+        |eq| if eq.is_valid(&operators) { eq.lhs } else { N::ZERO },
+        Some(N::ZERO),
+        |sum, lhs| sum?.checked_add(lhs))
+        .ok_or_else(|| anyhow!("sum of test values overflows"))
+}
+
+fn read_equations<N: Number>(reader: impl BufRead) -> Result<Vec<Equation<N>>>
+where
+    <N as FromStr>::Err: std::error::Error + Send + Sync + 'static,
+{
     let mut ret = Vec::new();
     let lines = read_lines(reader);
 
     for line in lines {
-        let parts = line.split(":").collect_vec();
-        let lhs = parts[0].parse::<i64>()?;
-        let operands = parts[1].split_whitespace()
-            .map(|x| x.parse::<i64>().unwrap())
-            .collect_vec();
+        let (lhs, operands) = line
+            .split_once(":")
+            .ok_or_else(|| anyhow!("invalid equation: {line}"))?;
+        let lhs = lhs.parse::<N>()?;
+        let operands = operands.split_whitespace()
+            .map(|x| x.parse::<N>())
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        if operands.is_empty() {
+            return Err(anyhow!("equation without operands: {line}"));
+        }
         ret.push(Equation { lhs, operands });
     }
 
     Ok(ret)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zero_operand() {
+        let equations = read_equations::<i64>("5: 7 0 5\n0: 3 4 0\n12: 3 4 0 12\n".as_bytes()).unwrap();
+        assert!(equations.iter().all(|eq| eq.is_valid(&[&Add, &Multiply])));
+        assert_eq!(sum_valid_equations(&equations, &["+", "*"]).unwrap(), 17);
+
+        let equations = read_equations::<u128>("5: 7 0 5\n705: 7 0 5\n".as_bytes()).unwrap();
+        assert_eq!(sum_valid_equations(&equations, &["+", "*", "||"]).unwrap(), 710);
+        assert!(!read_equations::<i64>("6: 7 0 5\n".as_bytes()).unwrap()[0].is_valid(&[&Add, &Multiply]));
+    }
+}
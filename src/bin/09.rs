@@ -4,6 +4,8 @@ use code_timing_macros::time_snippet;
 use const_format::concatcp;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 
 const DAY: &str = "09";
 const INPUT_FILE: &str = concatcp!("input/", DAY, ".txt");
@@ -19,11 +21,13 @@ fn main() -> Result<()> {
     println!("=== Part 1 ===");
 
     fn part1<R: BufRead>(reader: R) -> Result<usize> {
-        let blocks = read_disk_map(reader);
-        let compressed_blocks = compress(&blocks);
-        let result = checksum(&compressed_blocks);
+        let mut disk = Disk::new(&read_disk_map(reader));
+        disk.compact_blocks();
+        if disk.is_small() {
+            println!("{}", disk.render());
+        }
 
-        Ok(result)
+        Ok(disk.checksum())
     }
 
     assert_eq!(1928, part1(BufReader::new(TEST.as_bytes()))?);
@@ -37,11 +41,13 @@ fn main() -> Result<()> {
     println!("\n=== Part 2 ===");
 
     fn part2<R: BufRead>(reader: R) -> Result<usize> {
-        let blocks = read_disk_map(reader);
-        let moved_blocks = move_file_blocks(&blocks);
-        let result = checksum(&moved_blocks);
+        let mut disk = Disk::new(&read_disk_map(reader));
+        disk.compact_files();
+        if disk.is_small() {
+            println!("{}", disk.render());
+        }
 
-        Ok(result)
+        Ok(disk.checksum())
     }
 
     assert_eq!(2858, part2(BufReader::new(TEST.as_bytes()))?);
@@ -54,206 +60,134 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn checksum(blocks: &[Block]) -> usize {
-    let mut sum = 0;
-    let mut idx = 0_usize;
+const MAX_RENDER_SIZE: usize = 100;
+const MAX_SPAN_SIZE: usize = 9;
 
-    for block in blocks {
-        match block.block_type {
-            BlockType::File { id } => {
-                for _ in 0..block.size {
-                    sum += id * idx;
-                    idx += 1;
-                }
-            }
-            BlockType::Empty => {
-                for _ in 0..block.size {
-                    idx += 1;
-                }
-            }
-        }
-    }
+#[derive(Debug, Clone, Copy)]
+struct Span {
+    start: usize,
+    size: usize,
+}
 
-    sum
+/// Block level model of the disk. Free spans are indexed by their size, so the
+/// leftmost span that fits a file can be found without scanning the disk.
+#[derive(Debug)]
+struct Disk {
+    blocks: Vec<Option<usize>>,
+    files: Vec<Span>,
+    free_spans: Vec<BinaryHeap<Reverse<usize>>>,
+    checksum: usize,
 }
 
-fn move_file_blocks(blocks: &Vec<Block>) -> Vec<Block> {
-    let mut max_file_id = -1;
-    for block in blocks {
-        if let BlockType::File { id } = block.block_type {
-            if id as i32 > max_file_id {
-                max_file_id = id as i32;
+impl Disk {
+    fn new(disk_map: &[Block]) -> Disk {
+        let mut blocks = Vec::new();
+        let mut files = Vec::new();
+        let mut free_spans = vec![BinaryHeap::new(); MAX_SPAN_SIZE + 1];
+        let mut checksum = 0;
+
+        for block in disk_map {
+            let span = Span { start: blocks.len(), size: block.size as usize };
+            match block.block_type {
+                BlockType::File { id } => {
+                    checksum += Self::span_checksum(id, &span);
+                    blocks.extend(std::iter::repeat_n(Some(id), span.size));
+                    files.push(span);
+                }
+                BlockType::Empty => {
+                    if span.size > 0 {
+                        free_spans[span.size].push(Reverse(span.start));
+                    }
+                    blocks.extend(std::iter::repeat_n(None, span.size));
+                }
             }
         }
-    }
-    if max_file_id < 0 {
-        return blocks.clone();
+
+        Disk { blocks, files, free_spans, checksum }
     }
 
-    let mut result = blocks.clone();
-    while max_file_id >= 0 {
-        result = move_file_block(&result, max_file_id as usize);
-        max_file_id -= 1;
+    fn checksum(&self) -> usize {
+        self.checksum
     }
 
-    result
-}
+    fn span_checksum(id: usize, span: &Span) -> usize {
+        id * (span.start..span.start + span.size).sum::<usize>()
+    }
 
-fn move_file_block(blocks: &Vec<Block>, file_id: usize) -> Vec<Block> {
-    let mut new_blocks: Vec<Block> = Vec::new();
-    let mut idx_file: i32 = -1;
-    let mut required_size = 0;
+    /// Moves single blocks from the end of the disk to the leftmost free block
+    /// until there are no gaps left. Files get fragmented by this.
+    fn compact_blocks(&mut self) {
+        let mut left = 0;
+        let mut right = self.blocks.len();
 
-    for (idx, block) in blocks.iter().enumerate() {
-        if let BlockType::File { id } = block.block_type {
-            if id == file_id {
-                idx_file = idx as i32;
-                required_size = block.size;
+        loop {
+            while left < right && self.blocks[left].is_some() {
+                left += 1;
+            }
+            while right > left && self.blocks[right - 1].is_none() {
+                right -= 1;
+            }
+            if right - left < 2 {
                 break;
             }
+            right -= 1;
+            let id = self.blocks[right].unwrap();
+            self.blocks.swap(left, right);
+            self.checksum -= id * (right - left);
         }
     }
 
-    if idx_file == -1 {
-        return blocks.clone();
-    }
+    /// Moves each file, starting with the highest id, into the leftmost free span
+    /// that is large enough to hold the whole file.
+    fn compact_files(&mut self) {
+        for id in (0..self.files.len()).rev() {
+            let file = self.files[id];
+            if file.size == 0 {
+                continue;
+            }
 
-    let mut move_pending = true;
-
-    for block in blocks {
-        match block.block_type {
-            BlockType::File { id } => {
-                if id == file_id {
-                    if move_pending {
-                        new_blocks.push(block.clone());
-                        move_pending = false;
-                    } else {
-                        new_blocks.push(Block {
-                            block_type: BlockType::Empty,
-                            size: required_size,
-                        });
-                    }
-                } else {
-                    new_blocks.push(block.clone());
+            let best = (file.size..=MAX_SPAN_SIZE)
+                .filter_map(|size| self.free_spans[size].peek().map(|Reverse(start)| (*start, size)))
+                .min();
+
+            if let Some((start, size)) = best {
+                if start >= file.start {
+                    continue;
                 }
-            }
-            BlockType::Empty => {
-                if move_pending && block.size >= required_size {
-                    let file_block = blocks[idx_file as usize].clone();
-                    new_blocks.push(file_block);
-                    if block.size > required_size {
-                        new_blocks.push(Block {
-                            block_type: BlockType::Empty,
-                            size: block.size - required_size,
-                        });
-                    }
-                    move_pending = false;
-                } else {
-                    new_blocks.push(block.clone());
+                self.free_spans[size].pop();
+                if size > file.size {
+                    self.free_spans[size - file.size].push(Reverse(start + file.size));
                 }
+                self.move_file(id, start);
             }
         }
     }
 
-    new_blocks
-}
-
-fn compress(blocks: &Vec<Block>) -> Vec<Block> {
-    let mut new_blocks: Vec<Block> = blocks.clone();
+    fn move_file(&mut self, id: usize, new_start: usize) {
+        let old = self.files[id];
+        let new = Span { start: new_start, size: old.size };
 
-    loop {
-        if let Some(compressed) = compress_step(&new_blocks) {
-            new_blocks = compressed;
-        } else {
-            return new_blocks;
-        }
+        self.blocks[old.start..old.start + old.size].fill(None);
+        self.blocks[new.start..new.start + new.size].fill(Some(id));
+        self.checksum = self.checksum - Self::span_checksum(id, &old) + Self::span_checksum(id, &new);
+        self.files[id] = new;
     }
-}
-
-fn compress_step(blocks: &Vec<Block>) -> Option<Vec<Block>> {
-    let mut new_blocks: Vec<Block> = Vec::new();
-    let mut idx_first_empty: i32 = -1;
-    let mut idx_last_file: i32 = -1;
 
-    for (idx, block) in blocks.iter().enumerate() {
-        match block.block_type {
-            BlockType::Empty => {
-                if idx_first_empty == -1 && block.size > 0 {
-                    idx_first_empty = idx as i32;
-                }
+    fn render(&self) -> String {
+        let mut result = String::new();
+        for block in &self.blocks {
+            match block {
+                Some(id) => result.push_str(&format!("{id}")),
+                None => result.push('.'),
             }
-            BlockType::File { id: _ } => idx_last_file = idx as i32,
         }
-    }
-
-    if idx_first_empty == -1 {
-        return None;
-    }
-    if idx_last_file == -1 {
-        return None;
-    }
-    if idx_first_empty > idx_last_file {
-        return None;
-    }
 
-    let first_empty = &blocks[idx_first_empty as usize];
-    let last_file = &blocks[idx_last_file as usize];
-    let last_file_id = if let BlockType::File { id } = last_file.block_type {
-        id
-    } else {
-        panic!("Expected file block type")
-    };
-
-    for (idx, block) in blocks.iter().enumerate() {
-        if idx_first_empty == idx as i32 {
-            if last_file.size >= first_empty.size {
-                new_blocks.push(Block {
-                    block_type: BlockType::File { id: last_file_id },
-                    size: first_empty.size,
-                });
-            } else {
-                new_blocks.push(Block {
-                    block_type: BlockType::File { id: last_file_id },
-                    size: last_file.size,
-                });
-                new_blocks.push(Block {
-                    block_type: BlockType::Empty,
-                    size: first_empty.size - last_file.size,
-                });
-            }
-        } else if idx_last_file == idx as i32 {
-            if last_file.size > first_empty.size {
-                new_blocks.push(Block {
-                    block_type: BlockType::File { id: last_file_id },
-                    size: last_file.size - first_empty.size,
-                });
-            }
-        } else {
-            new_blocks.push(block.clone());
-        }
+        result
     }
 
-    Some(new_blocks)
-}
-
-fn blocks_to_string(blocks: &[Block]) -> String {
-    let mut result = String::new();
-    for block in blocks {
-        match block.block_type {
-            BlockType::Empty => {
-                for _ in 0..block.size {
-                    result.push('.');
-                }
-            }
-            BlockType::File { id } => {
-                for _ in 0..block.size {
-                    result.push_str(&format!("{id}"));
-                }
-            }
-        }
+    fn is_small(&self) -> bool {
+        self.blocks.len() <= MAX_RENDER_SIZE
     }
-
-    result
 }
 
 fn read_disk_map<R: BufRead>(reader: R) -> Vec<Block> {
@@ -2,13 +2,16 @@ use adv_code_2024::*;
 use anyhow::*;
 use code_timing_macros::time_snippet;
 use const_format::concatcp;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{self, Display, Formatter};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
 const DAY: &str = "11";
 const INPUT_FILE: &str = concatcp!("input/", DAY, ".txt");
 
+const LONG_RUN_BLINKS: usize = 10_000;
+
 const TEST: &str = "\
 125 17
 ";
@@ -20,9 +23,9 @@ fn main() -> Result<()> {
     println!("=== Part 1 ===");
 
     fn part1<R: BufRead>(reader: R) -> Result<usize> {
-        let stats = blink_n_times(&read_stone_stats(reader), 25);
-        let answer = stats.values().sum::<i64>();
-        Ok(answer as usize)
+        let stats = read_stone_stats(reader)?;
+        let num_stones: u128 = StoneEngine::plutonian().num_stones_after(&stats, 25)?;
+        Ok(usize::try_from(num_stones)?)
     }
 
     assert_eq!(55312, part1(BufReader::new(TEST.as_bytes()))?);
@@ -36,12 +39,17 @@ fn main() -> Result<()> {
     println!("\n=== Part 2 ===");
 
     fn part2<R: BufRead>(reader: R) -> Result<usize> {
-        let stats = blink_n_times(&read_stone_stats(reader), 75);
-        let answer = stats.values().sum::<i64>();
-        Ok(answer as usize)
+        let stats = read_stone_stats::<u128>(reader)?;
+        let evolution = StoneEngine::plutonian().blink_n_times(&stats, 75)?;
+        println!("Distinct stone values: {}", evolution.distinct_values.last().unwrap_or(&0));
+
+        let stats = stats.iter().map(|(stone, cnt)| (*stone, Modular::from(*cnt))).collect();
+        let long_run = StoneEngine::plutonian().num_stones_after(&stats, LONG_RUN_BLINKS)?;
+        println!("Stones after {LONG_RUN_BLINKS} blinks: {long_run}");
+        Ok(usize::try_from(evolution.num_stones()?)?)
     }
 
-    //assert_eq!(0, part2(BufReader::new(TEST.as_bytes()))?);
+    assert_eq!(65601038650482, part2(BufReader::new(TEST.as_bytes()))?);
 
     let input_file = BufReader::new(File::open(INPUT_FILE)?);
     let result = time_snippet!(part2(input_file)?);
@@ -51,67 +59,316 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-type StoneStats = HashMap<i64, i64>;
+type Stone = u64;
+type StoneStats<C> = HashMap<Stone, C>;
+
+/// Number of stones. Exact counts overflow after a few hundred blinks, so long runs
+/// are counted modulo a prime instead.
+trait Count: Clone + Default + Display {
+    fn one() -> Self;
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+}
+
+impl Count for u128 {
+    fn one() -> Self {
+        1
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        u128::checked_add(*self, *other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        u128::checked_mul(*self, *other)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+struct Modular(u64);
+
+impl Modular {
+    const MODULUS: u64 = 1_000_000_007;
+}
+
+impl From<u128> for Modular {
+    fn from(value: u128) -> Self {
+        Modular((value % Self::MODULUS as u128) as u64)
+    }
+}
 
-fn blink_n_times(stone_stats: &StoneStats, n: usize) -> StoneStats {
-    let mut stats = stone_stats.clone();
-    for _ in 0..n {
-        stats = blink(&stats);
+impl Display for Modular {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (mod {})", self.0, Self::MODULUS)
     }
-    stats
 }
 
-fn blink(stone_stats: &StoneStats) -> StoneStats {
-    let mut ret = HashMap::new();
+impl Count for Modular {
+    fn one() -> Self {
+        Modular(1)
+    }
 
-    for (stone, cnt) in stone_stats {
-        let (left, right) = transform(*stone);
-        *ret.entry(left).or_default() += cnt;
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(Modular((self.0 + other.0) % Self::MODULUS))
+    }
 
-        if let Some(right) = right {
-            *ret.entry(right).or_default() += cnt;
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        Some(Modular(self.0 * other.0 % Self::MODULUS))
+    }
+}
+
+fn add_counts<C: Count>(a: &C, b: &C) -> Result<C> {
+    a.checked_add(b).ok_or_else(|| anyhow!("number of stones overflows after {a} + {b}"))
+}
+
+trait Rule {
+    /// Returns the stones that replace `stone`, or `None` if the rule does not apply.
+    fn apply(&self, stone: Stone) -> Result<Option<Vec<Stone>>>;
+}
+
+struct ZeroBecomesOne;
+
+impl Rule for ZeroBecomesOne {
+    fn apply(&self, stone: Stone) -> Result<Option<Vec<Stone>>> {
+        Ok((stone == 0).then(|| vec![1]))
+    }
+}
+
+struct SplitEvenDigits;
+
+impl Rule for SplitEvenDigits {
+    fn apply(&self, stone: Stone) -> Result<Option<Vec<Stone>>> {
+        let num_digits = stone.checked_ilog10().unwrap_or(0) + 1;
+        if !num_digits.is_multiple_of(2) {
+            return Ok(None);
         }
+        let factor = (10 as Stone).pow(num_digits / 2);
+        Ok(Some(vec![stone / factor, stone % factor]))
     }
+}
+
+struct MultiplyBy(Stone);
 
-    ret
+impl Rule for MultiplyBy {
+    fn apply(&self, stone: Stone) -> Result<Option<Vec<Stone>>> {
+        let value = stone
+            .checked_mul(self.0)
+            .ok_or_else(|| anyhow!("stone {stone} times {} exceeds {}", self.0, Stone::MAX))?;
+        Ok(Some(vec![value]))
+    }
 }
 
-fn transform(stone: i64) -> (i64, Option<i64>) {
-    if stone == 0 {
-        return (1, None);
+struct Evolution<C> {
+    stats: StoneStats<C>,
+    /// Number of distinct stone values after each blink
+    distinct_values: Vec<usize>,
+}
+
+impl<C: Count> Evolution<C> {
+    fn num_stones(&self) -> Result<C> {
+        self.stats.values().try_fold(C::default(), |acc, cnt| add_counts(&acc, cnt))
     }
+}
 
-    let stone_str = stone.to_string();
+/// Number of stones that each stone turns into after `depth` blinks, for all stones
+/// reachable from the stones asked about so far. Stones are densely indexed so that
+/// deepening the table by one blink is a single pass over `children`.
+struct DepthTable<C> {
+    index: HashMap<Stone, usize>,
+    children: Vec<Vec<usize>>,
+    depth: usize,
+    counts: Vec<C>,
+}
 
-    if stone_str.len() % 2 == 0 {
-        let n = stone_str.len() / 2;
-        let left = stone_str.chars().take(n).collect::<String>();
-        let left = left.parse::<i64>().unwrap();
+impl<C> Default for DepthTable<C> {
+    fn default() -> Self {
+        Self { index: HashMap::new(), children: Vec::new(), depth: 0, counts: Vec::new() }
+    }
+}
+
+/// Evolves stones by applying the first matching rule to each stone. Stones are
+/// counted per value, the expansion of each value is computed only once, and the
+/// number of stones each value turns into is memoised by blink depth.
+struct StoneEngine<C> {
+    rules: Vec<Box<dyn Rule>>,
+    expansions: HashMap<Stone, Vec<Stone>>,
+    table: DepthTable<C>,
+}
+
+impl<C: Count> StoneEngine<C> {
+    fn new(rules: Vec<Box<dyn Rule>>) -> Self {
+        Self { rules, expansions: HashMap::new(), table: DepthTable::default() }
+    }
+
+    fn plutonian() -> Self {
+        Self::new(vec![
+            Box::new(ZeroBecomesOne),
+            Box::new(SplitEvenDigits),
+            Box::new(MultiplyBy(2024)),
+        ])
+    }
+
+    fn blink_n_times(&mut self, stone_stats: &StoneStats<C>, n: usize) -> Result<Evolution<C>> {
+        let mut stats = stone_stats.clone();
+        let mut distinct_values = Vec::with_capacity(n);
+        for _ in 0..n {
+            stats = self.blink(&stats)?;
+            distinct_values.push(stats.len());
+        }
+
+        Ok(Evolution { stats, distinct_values })
+    }
+
+    fn blink(&mut self, stone_stats: &StoneStats<C>) -> Result<StoneStats<C>> {
+        let mut ret: StoneStats<C> = HashMap::new();
+
+        for (stone, cnt) in stone_stats {
+            for new_stone in self.expand(*stone)?.clone() {
+                let entry = ret.entry(new_stone).or_default();
+                *entry = add_counts(entry, cnt)?;
+            }
+        }
+
+        Ok(ret)
+    }
+
+    /// Counts the stones after `n` blinks without tracking the individual values.
+    fn num_stones_after(&mut self, stone_stats: &StoneStats<C>, n: usize) -> Result<C> {
+        if n < self.table.depth || stone_stats.keys().any(|stone| !self.table.index.contains_key(stone)) {
+            self.build_table(stone_stats.keys().copied())?;
+        }
+        while self.table.depth < n {
+            self.deepen_table()?;
+        }
 
-        let right = stone_str.chars().skip(n).collect::<String>();
-        let mut right = right.trim_start_matches('0');
-        if right.len() == 0 {
-            right = "0";
+        stone_stats.iter().try_fold(C::default(), |acc, (stone, cnt)| {
+            let per_stone = &self.table.counts[self.table.index[stone]];
+            let total = per_stone
+                .checked_mul(cnt)
+                .ok_or_else(|| anyhow!("number of stones overflows after {per_stone} * {cnt}"))?;
+            add_counts(&acc, &total)
+        })
+    }
+
+    /// Restarts the table at depth 0 over all stones reachable from the known ones and `stones`.
+    fn build_table(&mut self, stones: impl Iterator<Item = Stone>) -> Result<()> {
+        let mut reachable = self.table.index.keys().copied().collect::<Vec<_>>();
+        reachable.extend(stones);
+        let mut index = HashMap::new();
+        let mut queue = VecDeque::new();
+        for stone in reachable {
+            if !index.contains_key(&stone) {
+                index.insert(stone, index.len());
+                queue.push_back(stone);
+            }
         }
-        let right = right.parse::<i64>().unwrap();
 
-        return (left, Some(right));
+        let mut children = Vec::new();
+        while let Some(stone) = queue.pop_front() {
+            let mut stone_children = Vec::new();
+            for child in self.expand(stone)?.clone() {
+                let next = index.len();
+                let idx = *index.entry(child).or_insert_with(|| {
+                    queue.push_back(child);
+                    next
+                });
+                stone_children.push(idx);
+            }
+            children.push(stone_children);
+        }
+
+        let counts = vec![C::one(); index.len()];
+        self.table = DepthTable { index, children, depth: 0, counts };
+        Ok(())
+    }
+
+    fn deepen_table(&mut self) -> Result<()> {
+        let table = &mut self.table;
+        let counts = table.children
+            .iter()
+            .map(|children| {
+                children
+                    .iter()
+                    .try_fold(C::default(), |acc, child| add_counts(&acc, &table.counts[*child]))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        table.counts = counts;
+        table.depth += 1;
+        Ok(())
     }
 
-    (stone * 2024, None)
+    fn expand(&mut self, stone: Stone) -> Result<&Vec<Stone>> {
+        if !self.expansions.contains_key(&stone) {
+            let mut expansion = None;
+            for rule in &self.rules {
+                expansion = rule.apply(stone)?;
+                if expansion.is_some() {
+                    break;
+                }
+            }
+            let expansion = expansion.ok_or_else(|| anyhow!("no rule applies to stone {stone}"))?;
+            self.expansions.insert(stone, expansion);
+        }
+
+        Ok(&self.expansions[&stone])
+    }
 }
 
-fn read_stone_stats(reader: impl BufRead) -> StoneStats {
+fn read_stone_stats<C: Count>(reader: impl BufRead) -> Result<StoneStats<C>> {
     let lines = read_lines(reader);
-    let line = lines[0].trim();
-    let numbers = line
-        .split_whitespace()
-        .map(|s| s.parse::<i64>().unwrap())
-        .collect::<Vec<i64>>();
-    let mut stats = HashMap::new();
-    for number in numbers {
-        *stats.entry(number).or_default() += 1;
+    let line = lines
+        .first()
+        .ok_or_else(|| anyhow!("missing stones"))?
+        .trim();
+    let mut stats: StoneStats<C> = HashMap::new();
+    for number in line.split_whitespace() {
+        let entry = stats.entry(number.parse::<Stone>()?).or_default();
+        *entry = add_counts(entry, &C::one())?;
     }
 
-    stats
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blink() {
+        let mut engine = StoneEngine::<u128>::plutonian();
+        let stats = HashMap::from([(0, 1), (1, 1), (10, 1), (99, 1), (999, 1)]);
+        let evolution = engine.blink_n_times(&stats, 1).unwrap();
+        assert_eq!(evolution.stats, HashMap::from([(1, 2), (2024, 1), (0, 1), (9, 2), (2021976, 1)]));
+        assert_eq!(evolution.distinct_values, vec![5]);
+        assert_eq!(engine.num_stones_after(&stats, 1).unwrap(), 7);
+    }
+
+    #[test]
+    fn test_many_blinks() {
+        let mut engine = StoneEngine::<u128>::plutonian();
+        let stats = HashMap::from([(125, 1), (17, 1)]);
+        let evolution = engine.blink_n_times(&stats, 150).unwrap();
+        let distinct = evolution.distinct_values.last().copied().unwrap();
+        assert!(distinct < 4000);
+        let exact = engine.num_stones_after(&stats, 150).unwrap();
+        assert_eq!(exact, evolution.num_stones().unwrap());
+
+        let mut engine = StoneEngine::<Modular>::plutonian();
+        let stats = HashMap::from([(125, Modular(1)), (17, Modular(1))]);
+        let modular = engine.num_stones_after(&stats, 150).unwrap();
+        assert_eq!(modular, Modular::from(exact));
+
+        let by_depth = engine.num_stones_after(&stats, 10_000).unwrap();
+        let by_value = engine.blink_n_times(&stats, 10_000).unwrap().num_stones().unwrap();
+        assert_eq!(by_depth, by_value);
+    }
+
+    #[test]
+    fn test_overflow() {
+        let mut engine = StoneEngine::<u128>::plutonian();
+        let error = engine.expand(Stone::MAX / 1000).unwrap_err();
+        assert!(error.to_string().contains("times 2024 exceeds"));
+        let stats = HashMap::from([(125, 1)]);
+        assert!(engine.num_stones_after(&stats, 10_000).is_err());
+    }
 }
@@ -1,11 +1,8 @@
 use adv_code_2024::*;
+use adv_code_2024::line_pattern::{Fields, FromCaptures, LinePattern};
 use anyhow::*;
 use code_timing_macros::time_snippet;
 use const_format::concatcp;
-use mathru::algebra::linear::matrix::{General, Solve};
-use mathru::algebra::linear::vector::Vector;
-use mathru::{matrix, vector};
-use regex::Regex;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
@@ -37,14 +34,12 @@ fn main() -> Result<()> {
     println!("=== Part 1 ===");
 
     fn part1<R: BufRead>(reader: R) -> Result<usize> {
-        let machines = read_machines(reader);
-        let mut total = 0_usize;
-
-        for machine in machines {
-            if let Some(costs) = machine.optimize_winning_costs_2() {
-                total += costs;
-            }
-        }
+        let machines = read_machines(reader)?;
+        let total = parallel::par_map_reduce(
+            &machines,
+            |machine| machine.optimize_winning_costs(),
+            0_usize,
+            |total, costs| total + costs.unwrap_or(0));
 
         Ok(total)
     }
@@ -60,20 +55,21 @@ fn main() -> Result<()> {
     println!("\n=== Part 2 ===");
 
     fn part2<R: BufRead>(reader: R) -> Result<usize> {
-        let mut machines = read_machines(reader);
-        let mut total = 0_usize;
-
+        let mut machines = read_machines(reader)?;
         for machine in machines.iter_mut() {
             machine.increase_prize_coords(10000000000000);
-            if let Some(costs) = machine.optimize_winning_costs_2() {
-                total += costs;
-            }
         }
 
+        let total = parallel::par_map_reduce(
+            &machines,
+            |machine| machine.optimize_winning_costs(),
+            0_usize,
+            |total, costs| total + costs.unwrap_or(0));
+
         Ok(total)
     }
 
-    //assert_eq!(0, part2(BufReader::new(TEST.as_bytes()))?);
+    assert_eq!(875318608908, part2(BufReader::new(TEST.as_bytes()))?);
 
     let input_file = BufReader::new(File::open(INPUT_FILE)?);
     let result = time_snippet!(part2(input_file)?);
@@ -96,6 +92,13 @@ impl Button {
     }
 }
 
+impl FromCaptures for Button {
+    fn from_captures(fields: &Fields) -> Result<Self> {
+        let cost = if fields.str("name")? == "A" { 3 } else { 1 };
+        Ok(Button::new(fields.get("x")?, fields.get("y")?, cost))
+    }
+}
+
 #[derive(Debug, Clone)]
 struct Prize {
     x: usize,
@@ -108,6 +111,12 @@ impl Prize {
     }
 }
 
+impl FromCaptures for Prize {
+    fn from_captures(fields: &Fields) -> Result<Self> {
+        Ok(Prize::new(fields.get("x")?, fields.get("y")?))
+    }
+}
+
 #[derive(Debug, Clone)]
 struct Machine {
     a: Button,
@@ -129,109 +138,224 @@ impl Machine {
         self.prize.y += amount;
     }
 
-    fn optimize_winning_costs_2(&self) -> Option<usize> {
-        let btn_a = &self.a;
-        let btn_b = &self.b;
-        let prize = &self.prize;
-
-        let m: General<f64> = matrix![
-          btn_a.x as f64, btn_b.x as f64;
-          btn_a.y as f64, btn_b.y as f64];
-
-        let b: Vector<f64> = vector![prize.x as f64; prize.y as f64];
+    fn optimize_winning_costs(&self) -> Option<usize> {
+        match self.solve() {
+            Solution::None => None,
+            Solution::Unique(presses) | Solution::Infinite { cheapest: presses } => {
+                usize::try_from(presses.cost(&self.a, &self.b)?).ok()
+            }
+        }
+    }
 
-        let solution = m.solve(&b);
+    /// Solves `a * btn_a + b * btn_b = prize` exactly for non-negative integers `a` and `b`.
+    fn solve(&self) -> Solution {
+        let (ax, ay) = (self.a.x as i128, self.a.y as i128);
+        let (bx, by) = (self.b.x as i128, self.b.y as i128);
+        let (px, py) = (self.prize.x as i128, self.prize.y as i128);
+
+        let det = ax * by - bx * ay;
+        if det != 0 {
+            // Cramer's rule
+            let num_a = px * by - bx * py;
+            let num_b = ax * py - px * ay;
+            if num_a % det != 0 || num_b % det != 0 {
+                return Solution::None;
+            }
+            let presses = Presses { a: num_a / det, b: num_b / det };
+            return if presses.a >= 0 && presses.b >= 0 {
+                Solution::Unique(presses)
+            } else {
+                Solution::None
+            };
+        }
 
-        if solution.is_ok() {
-            let solution = solution.unwrap();
-            let cnt_a = solution[0].round() as usize;
-            let cnt_b = solution[1].round() as usize;
-            let x = cnt_a * btn_a.x + cnt_b * btn_b.x;
-            let y = cnt_a * btn_a.y + cnt_b * btn_b.y;
+        // The button vectors are collinear, so the prize must lie on the same line
+        if ax * py - ay * px != 0 || bx * py - by * px != 0 {
+            return Solution::None;
+        }
 
-            if x == prize.x && y == prize.y {
-                Some(cnt_a * btn_a.cost + cnt_b * btn_b.cost)
+        if ax == 0 && bx == 0 && ay == 0 && by == 0 {
+            // Both buttons do not move the claw at all
+            return if px == 0 && py == 0 {
+                Solution::Infinite { cheapest: Presses { a: 0, b: 0 } }
             } else {
-                None
-            }
+                Solution::None
+            };
+        }
+
+        let (coeff_a, coeff_b, target) = if ax != 0 || bx != 0 {
+            (ax, bx, px)
         } else {
-            None
+            (ay, by, py)
+        };
+
+        match cheapest_on_line(coeff_a, coeff_b, target, self.a.cost as i128, self.b.cost as i128) {
+            Some(presses) => Solution::Infinite { cheapest: presses },
+            None => Solution::None,
         }
     }
+}
 
-    fn optimize_winning_costs(&self) -> Option<usize> {
-        let mut solutions = Vec::new();
-        let mut cnt_a = 0_usize;
-        let mut cnt_b;
-        let a = &self.a;
-        let b = &self.b;
-        let prize = &self.prize;
-
-        loop {
-            let (x, y) = (cnt_a * a.x, cnt_a * a.y);
-            if x > prize.x || y > prize.y {
-                break;
-            }
-            cnt_b = 0;
-            loop {
-                let (x, y) = (cnt_a * a.x + cnt_b * b.x, cnt_a * a.y + cnt_b * b.y);
-                if x > prize.x || y > prize.y {
-                    break;
-                }
-                if x == prize.x && y == prize.y {
-                    solutions.push((cnt_a, cnt_b, cnt_a * a.cost + cnt_b * b.cost));
-                }
-                cnt_b += 1;
-            }
-            cnt_a += 1;
-        }
+#[derive(Debug, Clone, PartialEq)]
+struct Presses {
+    a: i128,
+    b: i128,
+}
 
-        if !solutions.is_empty() {
-            solutions.sort_by(|a, b| a.2.cmp(&b.2));
-            Some(solutions[0].2)
-        } else {
-            None
+impl Presses {
+    fn cost(&self, btn_a: &Button, btn_b: &Button) -> Option<i128> {
+        self.a
+            .checked_mul(btn_a.cost as i128)?
+            .checked_add(self.b.checked_mul(btn_b.cost as i128)?)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Solution {
+    /// The prize cannot be reached with non-negative numbers of presses
+    None,
+    /// The button vectors are independent and exactly one combination reaches the prize
+    Unique(Presses),
+    /// The button vectors are collinear, so the linear system has infinitely many solutions
+    Infinite { cheapest: Presses },
+}
+
+/// Minimises `cost_a * a + cost_b * b` over all non-negative integers with
+/// `coeff_a * a + coeff_b * b = target`.
+fn cheapest_on_line(coeff_a: i128, coeff_b: i128, target: i128, cost_a: i128, cost_b: i128) -> Option<Presses> {
+    let (g, x, y) = extended_gcd(coeff_a, coeff_b);
+    if target % g != 0 {
+        return None;
+    }
+
+    // All solutions: a = a0 + k * v, b = b0 - k * u
+    let (a0, b0) = (x * (target / g), y * (target / g));
+    let (u, v) = (coeff_a / g, coeff_b / g);
+
+    let k_min = if v > 0 {
+        Some(div_ceil(-a0, v))
+    } else if a0 >= 0 {
+        None
+    } else {
+        return None;
+    };
+    let k_max = if u > 0 {
+        Some(div_floor(b0, u))
+    } else if b0 >= 0 {
+        None
+    } else {
+        return None;
+    };
+
+    if let (Some(k_min), Some(k_max)) = (k_min, k_max) {
+        if k_min > k_max {
+            return None;
         }
     }
+
+    let slope = cost_a * v - cost_b * u;
+    let k = if slope < 0 {
+        k_max?
+    } else {
+        k_min.or(k_max).unwrap_or(0)
+    };
+
+    Some(Presses { a: a0 + k * v, b: b0 - k * u })
+}
+
+/// Returns `(g, x, y)` with `g = gcd(a, b)` and `a * x + b * y = g`.
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
+    }
 }
 
-fn read_machines(reader: impl BufRead) -> Vec<Machine> {
-    let mut buttons = Vec::new();
-    let mut prize_opt: Option<Prize> = None;
+fn div_floor(a: i128, b: i128) -> i128 {
+    let q = a / b;
+    if a % b != 0 && (a < 0) != (b < 0) { q - 1 } else { q }
+}
+
+fn div_ceil(a: i128, b: i128) -> i128 {
+    -div_floor(-a, b)
+}
+
+fn read_machines(reader: impl BufRead) -> Result<Vec<Machine>> {
+    let a_pattern = LinePattern::<Button>::new(r"Button (?<name>A): X\+(?<x>\d+), Y\+(?<y>\d+)")?;
+    let b_pattern = LinePattern::<Button>::new(r"Button (?<name>B): X\+(?<x>\d+), Y\+(?<y>\d+)")?;
+    let prize_pattern = LinePattern::<Prize>::new(r"Prize: X=(?<x>\d+), Y=(?<y>\d+)")?;
     let mut machines = Vec::new();
-    let re = Regex::new(r"\d+").unwrap();
-
-    for line in read_lines(reader) {
-        if line.starts_with("Button") {
-            let mut steps = Vec::new();
-            for cap in re.captures_iter(&line) {
-                let step = cap.get(0).unwrap().as_str().parse::<usize>().unwrap();
-                steps.push(step);
-            }
-            if steps.len() == 2 {
-                let cost = if buttons.is_empty() { 3 } else { 1 };
-                let button = Button::new(steps[0], steps[1], cost);
-                buttons.push(button);
-            }
-        } else if line.starts_with("Prize") {
-            let mut coords = Vec::new();
-            for cap in re.captures_iter(&line) {
-                let coord = cap.get(0).unwrap().as_str().parse::<usize>().unwrap();
-                coords.push(coord);
-            }
-            if coords.len() == 2 {
-                prize_opt = Some(Prize::new(coords[0], coords[1]));
-            }
-        }
-        if buttons.len() == 2 {
-            if let Some(price) = &prize_opt {
-                let machine = Machine::new(&buttons[0], &buttons[1], price);
-                machines.push(machine);
-                buttons.clear();
-                prize_opt = None;
-            }
-        }
+
+    for section in read_sections(reader)? {
+        let [a, b, prize] = section.as_slice() else {
+            return Err(section[0].error("a machine needs two button lines and a prize line"));
+        };
+        let a = a_pattern.parse_line(a)?;
+        let b = b_pattern.parse_line(b)?;
+        let prize = prize_pattern.parse_line(prize)?;
+        machines.push(Machine::new(&a, &b, &prize));
     }
 
-    machines
+    Ok(machines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_machine(a: (usize, usize), b: (usize, usize), prize: (usize, usize)) -> Machine {
+        Machine::new(
+            &Button::new(a.0, a.1, 3),
+            &Button::new(b.0, b.1, 1),
+            &Prize::new(prize.0, prize.1))
+    }
+
+    #[test]
+    fn test_solve_unique() {
+        let machine = make_machine((94, 34), (22, 67), (8400, 5400));
+        assert_eq!(machine.solve(), Solution::Unique(Presses { a: 80, b: 40 }));
+        assert_eq!(machine.optimize_winning_costs(), Some(280));
+    }
+
+    #[test]
+    fn test_solve_no_solution() {
+        let machine = make_machine((26, 66), (67, 21), (12748, 12176));
+        assert_eq!(machine.solve(), Solution::None);
+    }
+
+    #[test]
+    fn test_solve_collinear() {
+        // A moves (3, 3) for 3 tokens, B moves (2, 2) for 1 token: B is cheaper per step
+        let machine = make_machine((3, 3), (2, 2), (13, 13));
+        assert_eq!(machine.solve(), Solution::Infinite { cheapest: Presses { a: 1, b: 5 } });
+
+        // A moves (4, 8) for 3 tokens, B moves (1, 2) for 1 token: A is cheaper per step
+        let machine = make_machine((4, 8), (1, 2), (9, 18));
+        assert_eq!(machine.solve(), Solution::Infinite { cheapest: Presses { a: 2, b: 1 } });
+
+        let machine = make_machine((2, 2), (4, 4), (7, 7));
+        assert_eq!(machine.solve(), Solution::None);
+
+        let machine = make_machine((2, 2), (4, 4), (6, 7));
+        assert_eq!(machine.solve(), Solution::None);
+
+        // Neither button moves the claw
+        let machine = make_machine((0, 0), (0, 0), (5, 0));
+        assert_eq!(machine.solve(), Solution::None);
+
+        let machine = make_machine((0, 0), (0, 0), (0, 0));
+        assert_eq!(machine.solve(), Solution::Infinite { cheapest: Presses { a: 0, b: 0 } });
+    }
+
+    #[test]
+    fn test_read_machines() {
+        let machines = read_machines(BufReader::new(TEST.as_bytes())).unwrap();
+        assert_eq!(machines.len(), 4);
+
+        let swapped = "Button B: X+22, Y+67\nButton A: X+94, Y+34\nPrize: X=8400, Y=5400\n";
+        let error = read_machines(BufReader::new(swapped.as_bytes())).unwrap_err();
+        assert!(format!("{error:#}").starts_with("line 1: \"Button B: X+22, Y+67\" does not match"));
+    }
 }
@@ -1,13 +1,15 @@
-use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use anyhow::*;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::ops::Range;
 use code_timing_macros::time_snippet;
 use const_format::concatcp;
 use itertools::Itertools;
-use regex::{Captures, Regex};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
 use adv_code_2024::*;
+use adv_code_2024::line_pattern::{Fields, FromCaptures, LinePattern};
 
 const DAY: &str = "14";
 const INPUT_FILE: &str = concatcp!("input/", DAY, ".txt");
@@ -26,74 +28,79 @@ p=7,3 v=-1,2
 p=2,4 v=2,-3
 p=9,5 v=-3,-3
 ";
+const TEST_CONFIG: Config = Config { width: 11, height: 7 };
+const INPUT_CONFIG: Config = Config { width: 101, height: 103 };
 
 fn main() -> Result<()> {
     start_day(DAY);
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let config = INPUT_CONFIG.with_args(&args)?;
 
     //region Part 1
     println!("=== Part 1 ===");
 
-    fn part1<R: BufRead>(reader: R, width: usize, height: usize) -> Result<usize> {
-        let robots = read_robots(reader);
-        let area = Area::new(width, height, robots);
-        area.move_robots(100);
+    fn part1<R: BufRead>(reader: R, config: &Config) -> Result<usize> {
+        let area = read_area(reader, config.width, config.height)?;
         
-        let counts = area.count_robots_per_quadrant();
-        let mut answer = 1;
-        for quadrant in 1..=4_usize {
-            match counts.get(&quadrant) {
-                Some(count) => { answer *= count; },
-                None => { answer = 0 }
-            }
-        }
-        
-        Ok(answer)
+        Ok(area.at(100).safety_factor())
     }
 
-    assert_eq!(12, part1(BufReader::new(TEST.as_bytes()), 11, 7)?);
+    assert_eq!(12, part1(BufReader::new(TEST.as_bytes()), &TEST_CONFIG)?);
 
     let input_file = BufReader::new(File::open(INPUT_FILE)?);
-    let result = time_snippet!(part1(input_file, 101, 103)?);
+    let result = time_snippet!(part1(input_file, &config)?);
     println!("Result = {}", result);
     //endregion
 
     //region Part 2
     println!("\n=== Part 2 ===");
     
-    fn part2<R: BufRead>(reader: R, width: usize, height: usize) -> Result<usize> {
-        let robots = read_robots(reader);
-        let area = Area::new(width, height, robots);
-        let mut time = 0;
-        const MIN_CLUSTER_SIZE: usize = 20;
+    fn part2<R: BufRead>(reader: R, config: &Config) -> Result<usize> {
+        let area = read_area(reader, config.width, config.height)?;
+        let (time, snapshot) = area.find_picture(Scoring::Variance);
+        println!("{}", snapshot.render());
         
-        loop {
-            println!("time: {}", time);
-            let max_cluster_size = area.find_max_cluster_size();
-            
-            if max_cluster_size >= MIN_CLUSTER_SIZE {
-                println!("max cluster size: {}", max_cluster_size);
-                area.print_robots();
-                return Ok(time);
-            }
-            
-            area.move_robots(1);
-            time += 1;
-            println!();
-        }
+        Ok(time)
     }
     
-    // assert_eq!(0, part2(BufReader::new(TEST.as_bytes()))?);
+    // assert_eq!(0, part2(BufReader::new(TEST.as_bytes()), &TEST_CONFIG)?);
     
     let input_file = BufReader::new(File::open(INPUT_FILE)?);
-    let result = time_snippet!(part2(input_file, 101, 103)?);
+    let result = time_snippet!(part2(input_file, &config)?);
     println!("Result = {}", result);
     //endregion
 
     Ok(())
 }
 
+/// Puzzle parameters, overridable with `--width` and `--height`.
+#[derive(Debug, Clone)]
+struct Config {
+    width: usize,
+    height: usize,
+}
+
+impl Config {
+    fn with_args(self, args: &[String]) -> Result<Self> {
+        check_options(args, &["--width", "--height"], &[])?;
+        let config = Config {
+            width: parse_option(args, "--width")?.unwrap_or(self.width),
+            height: parse_option(args, "--height")?.unwrap_or(self.height),
+        };
+        if config.width == 0 || config.height == 0 {
+            return Err(anyhow!("the area needs a width and a height of at least 1"));
+        }
+
+        Ok(config)
+    }
+}
+
 type Number = i32;
 
+/// Size of the window in which [`Scoring::Density`] counts robots
+const DENSITY_WINDOW_WIDTH: usize = 5;
+const DENSITY_WINDOW_HEIGHT: usize = 5;
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 struct Vec2 {
     x: Number, 
@@ -118,33 +125,223 @@ impl Robot {
     }
 }
 
+impl FromCaptures for Robot {
+    fn from_captures(fields: &Fields) -> Result<Self> {
+        let pos = Vec2::new(fields.get("x")?, fields.get("y")?);
+        let vel = Vec2::new(fields.get("vx")?, fields.get("vy")?);
+        Ok(Robot::new(pos, vel))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, EnumIter)]
+enum Quadrant {
+    NorthWest,
+    NorthEast,
+    SouthEast,
+    SouthWest,
+}
+
+/// Rectangular part of the area, given by half-open coordinate ranges.
+#[derive(Debug, Clone)]
+struct Region {
+    x: Range<Number>,
+    y: Range<Number>,
+}
+
+impl Region {
+    fn new(x: Range<Number>, y: Range<Number>) -> Region {
+        Region { x, y }
+    }
+
+    fn contains(&self, pos: &Vec2) -> bool {
+        self.x.contains(&pos.x) && self.y.contains(&pos.y)
+    }
+}
+
+#[derive(Debug, Clone, Copy, EnumIter)]
+enum Scoring {
+    /// Size of the largest cluster of adjacent robots
+    ClusterSize,
+    /// Spread of the robot positions around their center
+    Variance,
+    /// Number of runs in the run-length encoding of the frame
+    Compression,
+    /// Number of robots in the most crowded window of the area
+    Density,
+}
+
 #[derive(Debug, Clone)]
 struct Area {
     width: usize,
     height: usize,
-    robots: RefCell<Vec<Robot>>,
+    robots: Vec<Robot>,
 }
 
 impl Area {
     fn new(width: usize, height: usize, robots: Vec<Robot>) -> Area {
-        Area { width, height, robots: RefCell::new(robots) }
+        Area { width, height, robots }
     }
-    
-    fn get_robots(&self) -> Vec<Robot> {
-        self.robots.borrow().clone()
+
+    /// Number of seconds after which all robots are back at their start positions.
+    fn period(&self) -> usize {
+        self.width / gcd(self.width, self.height) * self.height
     }
-    
+
+    /// Returns the robot positions after `time` seconds.
+    fn at(&self, time: usize) -> Snapshot {
+        let time = (time % self.period()) as Number;
+        let positions = self.robots
+            .iter()
+            .map(|robot| Vec2::new(
+                Self::mov(robot.pos.x, robot.vel.x, time, self.width),
+                Self::mov(robot.pos.y, robot.vel.y, time, self.height)))
+            .collect();
+
+        Snapshot { width: self.width, height: self.height, positions }
+    }
+
+    /// Returns the time within one period that looks most like a picture according to
+    /// `scoring`, together with the snapshot at that time.
+    fn find_picture(&self, scoring: Scoring) -> (usize, Snapshot) {
+        let (best_time, _) = (0..self.period())
+            .map(|t| (t, self.at(t).score(scoring)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap();
+
+        (best_time, self.at(best_time))
+    }
+
+    fn mov(x: Number, v: Number, t: Number, limit: usize) -> Number {
+        (x + t * v).rem_euclid(limit as Number)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Snapshot {
+    width: usize,
+    height: usize,
+    positions: Vec<Vec2>,
+}
+
+impl Snapshot {
+    fn count_in(&self, region: &Region) -> usize {
+        self.positions
+            .iter()
+            .filter(|pos| region.contains(pos))
+            .count()
+    }
+
+    fn quadrant_region(&self, quadrant: Quadrant) -> Region {
+        let center_x = self.width as Number / 2;
+        let center_y = self.height as Number / 2;
+        let west = 0..center_x;
+        let east = center_x + 1..self.width as Number;
+        let north = 0..center_y;
+        let south = center_y + 1..self.height as Number;
+
+        match quadrant {
+            Quadrant::NorthWest => Region::new(west, north),
+            Quadrant::NorthEast => Region::new(east, north),
+            Quadrant::SouthEast => Region::new(east, south),
+            Quadrant::SouthWest => Region::new(west, south),
+        }
+    }
+
+    fn count_per_quadrant(&self) -> HashMap<Quadrant, usize> {
+        Quadrant::iter()
+            .map(|quadrant| (quadrant, self.count_in(&self.quadrant_region(quadrant))))
+            .collect()
+    }
+
+    fn safety_factor(&self) -> usize {
+        self.count_per_quadrant().values().product()
+    }
+
+    /// Counts the robots per cell of a grid with cells of size `cell_width` x `cell_height`.
+    #[allow(dead_code)]
+    fn heatmap(&self, cell_width: usize, cell_height: usize) -> Vec<Vec<usize>> {
+        let mut ret = vec![vec![0; self.width.div_ceil(cell_width)]; self.height.div_ceil(cell_height)];
+        for pos in &self.positions {
+            ret[pos.y as usize / cell_height][pos.x as usize / cell_width] += 1;
+        }
+        ret
+    }
+
+    /// Scores the snapshot. The lower the score, the more structured it is.
+    fn score(&self, scoring: Scoring) -> f64 {
+        match scoring {
+            Scoring::ClusterSize => -(self.find_max_cluster_size() as f64),
+            Scoring::Variance => self.variance(),
+            Scoring::Compression => self.count_runs() as f64,
+            Scoring::Density => {
+                -(self.max_window_count(DENSITY_WINDOW_WIDTH, DENSITY_WINDOW_HEIGHT) as f64)
+            }
+        }
+    }
+
+    /// Counts the robots in every window of the given size with a summed-area table
+    /// and returns the highest count.
+    fn max_window_count(&self, window_width: usize, window_height: usize) -> usize {
+        let window_width = window_width.min(self.width);
+        let window_height = window_height.min(self.height);
+
+        // sums[y][x] is the number of robots with a position below (x, y)
+        let mut sums = vec![vec![0_usize; self.width + 1]; self.height + 1];
+        for pos in &self.positions {
+            sums[pos.y as usize + 1][pos.x as usize + 1] += 1;
+        }
+        for y in 1..=self.height {
+            for x in 1..=self.width {
+                sums[y][x] += sums[y - 1][x] + sums[y][x - 1] - sums[y - 1][x - 1];
+            }
+        }
+
+        let mut ret = 0;
+        for y in window_height..=self.height {
+            for x in window_width..=self.width {
+                let count = sums[y][x] + sums[y - window_height][x - window_width]
+                    - sums[y - window_height][x] - sums[y][x - window_width];
+                ret = ret.max(count);
+            }
+        }
+
+        ret
+    }
+
+    fn variance(&self) -> f64 {
+        let n = self.positions.len() as f64;
+        let mean_x = self.positions.iter().map(|p| p.x as f64).sum::<f64>() / n;
+        let mean_y = self.positions.iter().map(|p| p.y as f64).sum::<f64>() / n;
+
+        self.positions
+            .iter()
+            .map(|p| (p.x as f64 - mean_x).powi(2) + (p.y as f64 - mean_y).powi(2))
+            .sum::<f64>() / n
+    }
+
+    fn count_runs(&self) -> usize {
+        1 + self.occupancy()
+            .windows(2)
+            .filter(|w| w[0] != w[1])
+            .count()
+    }
+
+    fn occupancy(&self) -> Vec<bool> {
+        let mut ret = vec![false; self.width * self.height];
+        for pos in &self.positions {
+            ret[pos.y as usize * self.width + pos.x as usize] = true;
+        }
+        ret
+    }
+
     fn find_max_cluster_size(&self) -> usize {
-        *self.find_clusters().iter().max().unwrap()
+        self.find_clusters().into_iter().max().unwrap_or(0)
     }
     
     fn find_clusters(&self) -> Vec<usize> {
         let mut ret = Vec::new();
         
-        let mut robot_positions = HashSet::new();
-        for robot in self.get_robots() {
-            robot_positions.insert(robot.pos);
-        }
+        let robot_positions: HashSet<Vec2> = self.positions.iter().copied().collect();
         
         let mut visited: HashSet<Vec2> = HashSet::new();
         
@@ -205,98 +402,22 @@ impl Area {
         ret
     }
     
-    
-    
-    fn print_robots(&self) {
-        let mut counts: HashMap<Vec2, usize> = HashMap::new();
-        for robot in self.get_robots() {
-            *counts.entry(robot.pos).or_default() += 1;
-        }
-        
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let cnt = counts.get(&Vec2::new(x as Number, y as Number));
-                match cnt {
-                    Some(_) => { print!("#"); }
-                    None => { print!("."); } 
-                }
-            }
-            println!();
-        }
-    }
-    
-    fn count_robots_per_quadrant(&self) -> HashMap<usize, usize> {
-        let mut counts = HashMap::new();
-        
-        for robot in self.robots.borrow().iter() {
-            match self.get_quadrant(&robot.pos) {
-                Some(q) => *counts.entry(q).or_insert(0) += 1,
-                None => continue,
-            }
-        }
-        
-        counts
-    }
-    
-    fn get_quadrant(&self, position: &Vec2) -> Option<usize> {
-        let center_x = self.width as i32 / 2;
-        let center_y = self.height as i32 / 2;
-        
-        match position.x.cmp(&center_x) {
-            std::cmp::Ordering::Less => match position.y.cmp(&center_y) {
-                std::cmp::Ordering::Less => Some(1),
-                std::cmp::Ordering::Equal => None,
-                std::cmp::Ordering::Greater => Some(4),
-            },
-            std::cmp::Ordering::Equal => None,
-            std::cmp::Ordering::Greater => match position.y.cmp(&center_y) {  
-                std::cmp::Ordering::Less => Some(2),
-                std::cmp::Ordering::Equal => None,
-                std::cmp::Ordering::Greater => Some(3),
-            },
-        }
-    }
-    
-    fn move_robots(&self, time: Number) {
-        for robot in self.robots.borrow_mut().iter_mut() {
-            self.move_robot(robot, time);
-        }
-    }
-    
-    fn move_robot(&self, robot: &mut Robot, time: Number) {
-        robot.pos.x = Self::mov(robot.pos.x, robot.vel.x, time, self.width);
-        robot.pos.y = Self::mov(robot.pos.y, robot.vel.y, time, self.height);
-    }
-    
-    fn mov(x: Number, v: Number, t: Number, limit: usize) -> Number {
-        let mut ret = x + t * v;
-        ret %= limit as Number;
-        if ret < 0 {
-            ret += limit as Number;
-        }
-        ret
+    fn render(&self) -> String {
+        self.occupancy()
+            .chunks(self.width)
+            .map(|row| row.iter().map(|o| if *o { '#' } else { '.' }).collect::<String>())
+            .join("\n")
     }
 }
 
-fn read_robots(reader: impl BufRead) -> Vec<Robot> {
-    let mut robots: Vec<Robot> = Vec::new();
-    let re = Regex::new(r"p=(.+),(.+)\s+v=(.+),(.+)").unwrap();
-    
-    for line in read_lines(reader) {
-        for cap in re.captures_iter(&line) {
-            let x = to_number(&cap, 1);
-            let y = to_number(&cap, 2);
-            let vx = to_number(&cap, 3);
-            let vy = to_number(&cap, 4);
-            robots.push(Robot::new(Vec2::new(x, y), Vec2::new(vx, vy)));
-        }
-    }
-    
-    robots
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
 }
 
-fn to_number(cap: &Captures, i: usize) -> Number {
-    cap.get(i).unwrap().as_str().parse::<Number>().unwrap()
+fn read_area(reader: impl BufRead, width: usize, height: usize) -> Result<Area> {
+    let pattern = LinePattern::<Robot>::new(r"p=(?<x>-?\d+),(?<y>-?\d+)\s+v=(?<vx>-?\d+),(?<vy>-?\d+)")?;
+
+    Ok(Area::new(width, height, pattern.read(reader)?))
 }
 
 #[cfg(test)]
@@ -307,10 +428,61 @@ mod tests {
     fn test_robot_move() {
         let robot = Robot::new(Vec2::new(2, 4), Vec2::new(2, -3));
         let area = Area::new(11, 7, vec![robot]);
-        area.move_robots(5);
-        let new_robots = area.get_robots();
-        assert_eq!(new_robots.len(), 1);
-        assert_eq!(new_robots[0].pos, Vec2::new(1, 3));
+        let snapshot = area.at(5);
+        assert_eq!(snapshot.positions, vec![Vec2::new(1, 3)]);
+        assert_eq!(area.at(5 + area.period()).positions, snapshot.positions);
+    }
+    
+    #[test]
+    fn test_robot_move_wraps_at_edges() {
+        let robots = vec![
+            Robot::new(Vec2::new(0, 0), Vec2::new(-1, -1)),
+            Robot::new(Vec2::new(10, 6), Vec2::new(1, 1)),
+            Robot::new(Vec2::new(5, 3), Vec2::new(-23, 15)),
+        ];
+        let area = Area::new(11, 7, robots);
+        assert_eq!(area.at(1).positions, vec![
+            Vec2::new(10, 6),
+            Vec2::new(0, 0),
+            Vec2::new(4, 4)]);
+        assert_eq!(area.at(2).positions, vec![
+            Vec2::new(9, 5),
+            Vec2::new(1, 1),
+            Vec2::new(3, 5)]);
+    }
+    
+    #[test]
+    fn test_quadrants() {
+        let area = read_area(BufReader::new(TEST.as_bytes()), 11, 7).unwrap();
+        let snapshot = area.at(100);
+        let counts = snapshot.count_per_quadrant();
+        assert_eq!(counts[&Quadrant::NorthWest], 1);
+        assert_eq!(counts[&Quadrant::NorthEast], 3);
+        assert_eq!(counts[&Quadrant::SouthEast], 1);
+        assert_eq!(counts[&Quadrant::SouthWest], 4);
+        assert_eq!(snapshot.safety_factor(), 12);
+        assert_eq!(snapshot.count_in(&Region::new(0..11, 0..7)), 12);
+        assert_eq!(snapshot.heatmap(6, 4).iter().flatten().sum::<usize>(), 12);
+    }
+    
+    #[test]
+    fn test_find_picture() {
+        // Nine robots that meet in a 3x3 block at time 4 and scatter otherwise
+        let mut robots = Vec::new();
+        for (i, (x, y)) in (0..3).cartesian_product(0..3).enumerate() {
+            let vel = Vec2::new(i as Number - 4, 2 * i as Number - 3);
+            let pos = Vec2::new(
+                (5 + x - 4 * vel.x).rem_euclid(11),
+                (3 + y - 4 * vel.y).rem_euclid(7));
+            robots.push(Robot::new(pos, vel));
+        }
+        let area = Area::new(11, 7, robots);
+        
+        for scoring in Scoring::iter() {
+            let (time, snapshot) = area.find_picture(scoring);
+            assert_eq!(time, 4, "{:?}", scoring);
+            assert_eq!(snapshot.find_max_cluster_size(), 9);
+        }
     }
     
 }
\ No newline at end of file
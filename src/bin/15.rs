@@ -1,7 +1,6 @@
-use std::collections::{HashSet, VecDeque};
 use anyhow::*;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, BufWriter, Write};
 use code_timing_macros::time_snippet;
 use const_format::concatcp;
 use adv_code_2024::*;
@@ -59,7 +58,7 @@ fn main() -> Result<()> {
     
     fn part2<R: BufRead>(reader: R) -> Result<usize> {
         let (warehouse, movements) = read_warehouse(reader)?;
-        let mut warehouse = Warehouse2::from_warehouse(&warehouse);
+        let mut warehouse = warehouse.widen(2);
         
         warehouse.move_robot(&movements);
         Ok(warehouse.gps_box_sum())
@@ -72,400 +71,342 @@ fn main() -> Result<()> {
     println!("Result = {}", result);
     //endregion
 
+    //region Replay
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if let Some(command) = ReplayCommand::from_args(&args)? {
+        let (warehouse, movements) = read_warehouse(BufReader::new(File::open(INPUT_FILE)?))?;
+        let mut replay = Replay::new(warehouse.widen(2), &movements);
+        replay.run(&command)?;
+    }
+    //endregion
+
     Ok(())
 }
 
-#[derive(Debug, Clone, Copy)]
-enum Cell2 {
-    Robot,
-    BoxLeft,
-    BoxRight,
-    Wall,
+type BoxId = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Cell {
     Empty,
+    Wall,
+    Box(BoxId),
 }
 
-#[derive(Debug)]
-struct Warehouse2 {
-    grid: Grid<Cell2>,
+/// A box covers `width` cells to the east of its position.
+#[derive(Debug, Clone)]
+struct WarehouseBox {
+    pos: Position,
+    width: i32,
+}
+
+impl WarehouseBox {
+    fn positions(&self) -> impl Iterator<Item = Position> + '_ {
+        (0..self.width).map(|dc| Position::new(self.pos.row(), self.pos.col() + dc))
+    }
+}
+
+/// A single robot step: the direction and the boxes that were pushed along.
+#[derive(Debug, Clone)]
+struct Move {
+    direction: Direction,
+    pushed: Vec<BoxId>,
+}
+
+type MoveLog = Vec<Move>;
+
+#[derive(Debug, Clone)]
+struct Warehouse {
+    grid: Grid<Cell>,
+    boxes: Vec<WarehouseBox>,
     robot_pos: Position,
 }
 
-impl Warehouse2 {
-    fn from_warehouse(warehouse: &Warehouse) -> Self {
-        let grid = &warehouse.grid;
-        let num_rows = grid.num_rows;
-        let num_cols = grid.num_cols * 2;
-        let robot_pos = Position::new(warehouse.robot_pos.row(), 
-                                      warehouse.robot_pos.col() * 2);
-        let mut cells = Vec::new();
-        
-        for row in &grid.cells {
-            let mut row_cells = Vec::new();
-            for cell in row {
-                match cell {
-                    Cell::Empty => {
-                        row_cells.push(Cell2::Empty);
-                        row_cells.push(Cell2::Empty);
-                    }
-                    Cell::Robot => {
-                        row_cells.push(Cell2::Robot);
-                        row_cells.push(Cell2::Empty);
-                    }
-                    Cell::Box => {
-                        row_cells.push(Cell2::BoxLeft);
-                        row_cells.push(Cell2::BoxRight);
-                    }
-                    Cell::Wall => {
-                        row_cells.push(Cell2::Wall);
-                        row_cells.push(Cell2::Wall);
-                    }
-                }
-            }
-            cells.push(row_cells);
-        }
-        
-        Warehouse2 { grid: Grid::new(num_rows, num_cols, cells), robot_pos }
+impl Warehouse {
+    fn new(grid: Grid<Cell>, boxes: Vec<WarehouseBox>, robot_pos: Position) -> Self {
+        Self { grid, boxes, robot_pos }
     }
 
-    fn gps_box_sum(&self) -> usize {
-        let mut total = 0;
-        for (y, row) in self.grid.cells.iter().enumerate() {
-            for (x, cell) in row.iter().enumerate() {
-                match cell {
-                    Cell2::BoxLeft => {
-                        total += 100 * y + x;
-                    }
-                    _ => continue,
-                }
-            }
-        }
+    /// Stretches the warehouse horizontally by `factor`, so walls and boxes get wider.
+    fn widen(&self, factor: i32) -> Self {
+        let cells = self.grid.cells
+            .iter()
+            .map(|row| row
+                .iter()
+                .flat_map(|cell| std::iter::repeat_n(*cell, factor as usize))
+                .collect())
+            .collect();
+        let grid = Grid::new(self.grid.num_rows, self.grid.num_cols * factor, cells);
+        let boxes = self.boxes
+            .iter()
+            .map(|b| WarehouseBox {
+                pos: Position::new(b.pos.row(), b.pos.col() * factor),
+                width: b.width * factor,
+            })
+            .collect();
+        let robot_pos = Position::new(self.robot_pos.row(), self.robot_pos.col() * factor);
+
+        Self::new(grid, boxes, robot_pos)
+    }
 
-        total
+    fn gps_box_sum(&self) -> usize {
+        self.boxes
+            .iter()
+            .map(|b| 100 * b.pos.row() as usize + b.pos.col() as usize)
+            .sum()
     }
-    
-    fn move_robot(&mut self, movements: &Movements) {
-        movements.iter().for_each(|movement| {
-            self.move_robot_one_step(movement);
-        });
+
+    fn move_robot(&mut self, movements: &Movements) -> MoveLog {
+        movements
+            .iter()
+            .filter_map(|movement| self.move_robot_one_step(movement))
+            .collect()
     }
-    
-    fn move_robot_one_step(&mut self, direction: &Direction) {
-        let target_pos = self.robot_pos.make_step(direction);
-        let target_value = self.grid.value_at(&target_pos);
-        if target_value.is_none() {
-            return;
-        }
-        let target_value = target_value.unwrap();
 
-        match target_value {
-            Cell2::Empty => {
-            }
-            Cell2::BoxLeft | Cell2::BoxRight => {
-                if self.can_box_move(&target_pos, direction) {
-                    let box_move_positions = self.determine_box_move_order(&target_pos, direction);
-                    box_move_positions.iter().for_each(|position| {
-                        self.move_box(position, direction);
-                    });
-                } else {
-                    return;
-                }
-            }
-            _ => { return; }
-        }
+    /// Moves the robot and pushes all boxes in its way. Returns `None` if the
+    /// robot is blocked.
+    fn move_robot_one_step(&mut self, direction: &Direction) -> Option<Move> {
+        let target_pos = self.robot_pos.make_step(direction);
+        let pushed = self.find_pushed_boxes(&target_pos, direction)?;
 
-        self.grid.set_value_at(&self.robot_pos, Cell2::Empty);
-        self.grid.set_value_at(&target_pos, Cell2::Robot);
+        self.shift_boxes(&pushed, direction);
         self.robot_pos = target_pos;
+
+        Some(Move { direction: direction.clone(), pushed })
     }
-    
-    fn can_box_move(&self, from_pos: &Position, direction: &Direction) -> bool {
-        let (left_pos, right_pos) = self.get_all_box_positions(from_pos);
-        let to_positions = self.get_box_destinations(&left_pos, &right_pos, direction);
-
-        for to_pos in &to_positions {
-            let to_value = self.grid.value_at(to_pos);
-            if to_value.is_none() {
-                return false;
-            }
-            let to_value = to_value.unwrap();
 
-            match to_value {
-                Cell2::Empty => continue,
-                Cell2::BoxLeft | Cell2::BoxRight => {
-                    if !self.can_box_move(to_pos, direction) {
-                        return false;
-                    }
-                }
-                _ => { return false; }
-            }
-        }
-        
-        true
+    fn redo(&mut self, mv: &Move) {
+        self.shift_boxes(&mv.pushed, &mv.direction);
+        self.robot_pos = self.robot_pos.make_step(&mv.direction);
     }
-    
-    fn determine_box_move_order(&self, 
-                                box_pos: &Position, 
-                                direction: &Direction) -> Vec<Position> {
 
-        use Cell2::*;
-        
-        let mut box_moves = Vec::new();
-        let mut todos = VecDeque::new();
-        let mut processed: HashSet<Position> = HashSet::new();
-        todos.push_back(box_pos.clone());
-        
-        while let Some(current) = todos.pop_front() {
-            if processed.contains(&current) {
-                continue;
-            }
-            processed.insert(current.clone());
-            box_moves.insert(0, current.clone());
-
-            let (left_pos, right_pos) = self.get_all_box_positions(&current);
-            let destinations = self.get_box_destinations(&left_pos, &right_pos, direction);
-            
-            for destination in &destinations {
-                let destination_value = self.grid.value_at(destination);
-                match destination_value {
-                    Some(destination_value) => {
-                        let next_box = match destination_value {
-                            BoxLeft => destination.clone(),
-                            BoxRight => destination.make_step(&Direction::West),
-                            _ => continue,
-                        };
-                        if processed.contains(&next_box) {
-                            continue;
+    fn undo(&mut self, mv: &Move) {
+        let back = mv.direction.turn_right().turn_right();
+        self.shift_boxes(&mv.pushed, &back);
+        self.robot_pos = self.robot_pos.make_step(&back);
+    }
+
+    /// Collects the boxes that have to move if something enters `pos` from the
+    /// given direction. Returns `None` if a wall blocks the chain.
+    fn find_pushed_boxes(&self, pos: &Position, direction: &Direction) -> Option<Vec<BoxId>> {
+        let mut pushed = Vec::new();
+        let mut todo = vec![pos.clone()];
+
+        while let Some(current) = todo.pop() {
+            match self.grid.value_at(&current)? {
+                Cell::Empty => {}
+                Cell::Wall => return None,
+                Cell::Box(id) => {
+                    if pushed.contains(id) {
+                        continue;
+                    }
+                    pushed.push(*id);
+                    for box_pos in self.boxes[*id].positions() {
+                        let next = box_pos.make_step(direction);
+                        if self.grid.value_at(&next) != Some(&Cell::Box(*id)) {
+                            todo.push(next);
                         }
-                        todos.push_back(next_box.clone());
                     }
-                    None => continue,
                 }
             }
         }
-        
-        box_moves
+
+        Some(pushed)
     }
 
-    fn move_box(&mut self, from_pos: &Position, direction: &Direction) {
-        let (left_pos, right_pos) = self.get_all_box_positions(from_pos);
-        let destinations = self.get_box_destinations(&left_pos, &right_pos, direction);
-
-        match direction {
-            Direction::North | Direction::South=> {
-                let new_left = &destinations[0];
-                let new_right = &destinations[1];
-                self.grid.set_value_at(&left_pos, Cell2::Empty);
-                self.grid.set_value_at(&right_pos, Cell2::Empty);
-                self.grid.set_value_at(new_left, Cell2::BoxLeft);
-                self.grid.set_value_at(new_right, Cell2::BoxRight);
-            }
-            Direction::East => {
-                let new_right = &destinations[0];
-                self.grid.set_value_at(new_right, Cell2::BoxRight);
-                self.grid.set_value_at(&right_pos, Cell2::BoxLeft);
-                self.grid.set_value_at(&left_pos, Cell2::Empty);
-            }
-            Direction::West => {
-                let new_left = &destinations[0];
-                self.grid.set_value_at(new_left, Cell2::BoxLeft);
-                self.grid.set_value_at(&left_pos, Cell2::BoxRight);
-                self.grid.set_value_at(&right_pos, Cell2::Empty);
+    fn shift_boxes(&mut self, ids: &[BoxId], direction: &Direction) {
+        for id in ids {
+            for pos in self.boxes[*id].positions().collect::<Vec<_>>() {
+                self.grid.set_value_at(&pos, Cell::Empty);
             }
         }
-    }
-    
-    fn get_box_destinations(&self, 
-                            left_pos: &Position, 
-                            right_pos: &Position, 
-                            direction: &Direction) -> Vec<Position> {
-        match direction {
-            Direction::North | Direction::South => {
-                vec![
-                    left_pos.make_step(direction),
-                    right_pos.make_step(direction),
-                ]
-            }
-            Direction::East => {
-                vec![right_pos.make_step(direction)]
-            }
-            Direction::West => {
-                vec![left_pos.make_step(direction)]
+        for id in ids {
+            let warehouse_box = &mut self.boxes[*id];
+            warehouse_box.pos = warehouse_box.pos.make_step(direction);
+            for pos in self.boxes[*id].positions().collect::<Vec<_>>() {
+                self.grid.set_value_at(&pos, Cell::Box(*id));
             }
         }
     }
-    
-    fn get_all_box_positions(&self, position: &Position) -> (Position, Position) {
-        let box_value = self.grid.value_at(position).unwrap();
-        let left_pos: Position;
-        let right_pos: Position;
-
-        match box_value {
-            Cell2::BoxLeft => {
-                left_pos = position.clone();
-                right_pos = left_pos.make_step(&Direction::East);
-            }
-            Cell2::BoxRight => {
-                right_pos = position.clone();
-                left_pos = right_pos.make_step(&Direction::West);
+
+    fn render(&self) -> String {
+        let mut ret = String::new();
+        for row in 0..self.grid.num_rows {
+            for col in 0..self.grid.num_cols {
+                let pos = Position::new(row, col);
+                let ch = match self.grid.value_at(&pos).unwrap() {
+                    _ if pos == self.robot_pos => '@',
+                    Cell::Empty => '.',
+                    Cell::Wall => '#',
+                    Cell::Box(id) => {
+                        let warehouse_box = &self.boxes[*id];
+                        if warehouse_box.width == 1 {
+                            'O'
+                        } else if col == warehouse_box.pos.col() {
+                            '['
+                        } else if col == warehouse_box.pos.col() + warehouse_box.width - 1 {
+                            ']'
+                        } else {
+                            '='
+                        }
+                    }
+                };
+                ret.push(ch);
             }
-            _ => panic!("must not happen!")
+            ret.push('\n');
         }
-        
-        (left_pos, right_pos)
+
+        ret
     }
 
     #[allow(dead_code)]
     fn print(&self) {
-        for line in &self.grid.cells {
-            for cell in line {
-                match cell {
-                    Cell2::Robot => { print!("@"); }
-                    Cell2::BoxLeft => { print!("["); }
-                    Cell2::BoxRight => { print!("]"); }
-                    Cell2::Wall => { print!("#"); }
-                    Cell2::Empty => { print!("."); }
-                }
-            }
-            println!();
-        }
-        println!();
+        println!("{}", self.render());
     }
-
 }
 
+/// Replay modes, selected on the command line:
+/// `--step N` prints the warehouse after N movements,
+/// `--pushes` prints every frame in which boxes moved,
+/// `--animate FILE` writes all frames as an ANSI animation to FILE.
 #[derive(Debug)]
-enum Cell {
-    Robot,
-    Box,
-    Wall,
-    Empty,
+enum ReplayCommand {
+    Step(usize),
+    Pushes,
+    Animate(String),
 }
 
-#[derive(Debug)]
-struct Warehouse {
-    grid: Grid<Cell>,
-    robot_pos: Position,
+impl ReplayCommand {
+    fn from_args(args: &[String]) -> Result<Option<Self>> {
+        check_options(args, &["--step", "--animate"], &["--pushes"])?;
+        let mut commands = Vec::new();
+        if let Some(step) = parse_option(args, "--step")? {
+            commands.push(ReplayCommand::Step(step));
+        }
+        if has_flag(args, "--pushes") {
+            commands.push(ReplayCommand::Pushes);
+        }
+        if let Some(file_name) = parse_option(args, "--animate")? {
+            commands.push(ReplayCommand::Animate(file_name));
+        }
+        if commands.len() > 1 {
+            return Err(anyhow!("only one of --step, --pushes and --animate can be given"));
+        }
+
+        Ok(commands.pop())
+    }
 }
 
-impl Warehouse {
-    fn new(grid: Grid<Cell>, robot_pos: Position) -> Self {
-        Self { grid, robot_pos }
+/// Steps back and forth through the recorded robot movements. Only one
+/// warehouse is kept, frames are reached by redoing or undoing moves.
+struct Replay {
+    warehouse: Warehouse,
+    steps: Vec<Option<Move>>,
+    current: usize,
+}
+
+impl Replay {
+    fn new(mut warehouse: Warehouse, movements: &Movements) -> Self {
+        let steps = movements
+            .iter()
+            .map(|movement| warehouse.move_robot_one_step(movement))
+            .collect::<Vec<_>>();
+        let current = steps.len();
+
+        let mut replay = Self { warehouse, steps, current };
+        replay.seek(0);
+        replay
     }
-    
-    fn gps_box_sum(&self) -> usize {
-        let mut total = 0;
-        for (y, row) in self.grid.cells.iter().enumerate() {
-            for (x, cell) in row.iter().enumerate() {
-                match cell {
-                    Cell::Box => {
-                        total += 100 * y + x;               
-                    }
-                    _ => continue,
-                }
-            }
-        }
-        
-        total
+
+    fn num_steps(&self) -> usize {
+        self.steps.len()
     }
 
-    #[allow(dead_code)]
-    fn print(&self) {
-        for line in &self.grid.cells {
-            for cell in line {
-                match cell {
-                    Cell::Robot => { print!("@"); }
-                    Cell::Box => { print!("O"); }
-                    Cell::Wall => { print!("#"); }
-                    Cell::Empty => { print!("."); }
-                }
+    /// Moves to the frame after `step` movements.
+    fn seek(&mut self, step: usize) -> &Warehouse {
+        let step = step.min(self.num_steps());
+        while self.current < step {
+            if let Some(mv) = &self.steps[self.current] {
+                self.warehouse.redo(mv);
             }
-            println!();
+            self.current += 1;
         }
-        println!();
+        while self.current > step {
+            self.current -= 1;
+            if let Some(mv) = &self.steps[self.current] {
+                self.warehouse.undo(mv);
+            }
+        }
+
+        &self.warehouse
     }
-    
-    fn move_robot(&mut self, movements: &Movements) {
-        movements.iter().for_each(|movement| {
-            self.move_robot_one_step(movement);
-        })
+
+    /// Frames that follow a movement in which at least one box was pushed.
+    fn box_move_frames(&self) -> Vec<usize> {
+        self.steps
+            .iter()
+            .enumerate()
+            .filter(|(_, mv)| mv.as_ref().is_some_and(|mv| !mv.pushed.is_empty()))
+            .map(|(idx, _)| idx + 1)
+            .collect()
     }
-    
-    fn move_robot_one_step(&mut self, direction: &Direction) {
-        let target_pos = self.robot_pos.make_step(direction);
-        let target = self.grid.value_at(&target_pos);
-        
-        if target.is_none() {
-            return;
-        }
-        let target = target.unwrap();
-        
-        match target {
-            Cell::Empty => {},
-            Cell::Box => {
-                if let Some(empty_pos) = self.find_empty_cell(&target_pos, direction) {
-                    self.grid.set_value_at(&empty_pos, Cell::Box);
-                } else {
-                    return;
-                }
-            }
-            _ => { return; }
+
+    fn write_animation(&mut self, mut writer: impl Write) -> Result<()> {
+        const CLEAR_SCREEN: &str = "\x1b[H\x1b[2J";
+        const ROBOT: &str = "\x1b[1;33m@\x1b[0m";
+
+        for step in 0..=self.num_steps() {
+            let frame = self.seek(step).render().replace('@', ROBOT);
+            write!(writer, "{CLEAR_SCREEN}Step {step}/{}\n{frame}", self.num_steps())?;
         }
 
-        self.grid.set_value_at(&self.robot_pos, Cell::Empty);
-        self.grid.set_value_at(&target_pos, Cell::Robot);
-        self.robot_pos = target_pos;
+        Ok(())
     }
-    
-    fn find_empty_cell(&self, start_pos: &Position, direction: &Direction) -> Option<Position> {
-        let mut pos = start_pos.clone();
-        while self.grid.is_valid_position(&pos) {
-            let cell = self.grid.value_at(&pos).unwrap();
-            match cell {
-                Cell::Empty => return Some(pos),
-                Cell::Box => {
-                    pos = pos.make_step(direction);
+
+    fn run(&mut self, command: &ReplayCommand) -> Result<()> {
+        match command {
+            ReplayCommand::Step(step) => {
+                println!("Step {step}/{}", self.num_steps());
+                println!("{}", self.seek(*step).render());
+            }
+            ReplayCommand::Pushes => {
+                for step in self.box_move_frames() {
+                    println!("Step {step}/{}", self.num_steps());
+                    println!("{}", self.seek(step).render());
                 }
-                _ => return None,
+            }
+            ReplayCommand::Animate(file_name) => {
+                self.write_animation(BufWriter::new(File::create(file_name)?))?;
             }
         }
-        None
+
+        Ok(())
     }
 }
 
 type Movements = Vec<Direction>;
 
 fn read_warehouse<R: BufRead>(reader: R) -> Result<(Warehouse, Movements)> {
-    let lines = read_lines(reader); 
-    let mut warehouse_lines = Vec::new();
-    let mut movement_lines = Vec::new();
-    let mut parse_warehouse = true;
-    
-    for line in lines {
-        if line.is_empty() {
-            parse_warehouse = false;
-            continue;
-        }
-        if parse_warehouse {
-            warehouse_lines.push(line);
-        } else {
-            movement_lines.push(line);
-        }
-    }
-    
+    let (warehouse_lines, movement_lines) = read_two_sections(reader)?;
+
     let num_rows = warehouse_lines.len() as i32;
-    let num_columns = warehouse_lines[0].len() as i32;
+    let num_columns = warehouse_lines[0].text.len() as i32;
     let mut cells = Vec::new();
+    let mut boxes = Vec::new();
     let mut robot_pos: Option<Position> = None;
     
     for (r, line) in warehouse_lines.iter().enumerate() {
         let mut row = Vec::new();
-        for (c, ch) in line.chars().enumerate() {
+        for (c, ch) in line.text.chars().enumerate() {
+            let pos = Position::new(r as i32, c as i32);
             let cell = match ch {
               '@' => {
-                  robot_pos = Some(Position::new(r as i32, c as i32));
-                  Cell::Robot
+                  robot_pos = Some(pos);
+                  Cell::Empty
+              },
+              'O' => {
+                  boxes.push(WarehouseBox { pos, width: 1 });
+                  Cell::Box(boxes.len() - 1)
               },
-              'O' => Cell::Box,
               '#' => Cell::Wall,
               _ => Cell::Empty,
             };
@@ -474,25 +415,71 @@ fn read_warehouse<R: BufRead>(reader: R) -> Result<(Warehouse, Movements)> {
         cells.push(row);
     }
     
-    if robot_pos.is_none() {
-        return Err(Error::msg("no robot given"));
-    }
-    let robot_pos = robot_pos.unwrap();
+    let robot_pos = robot_pos.ok_or_else(|| anyhow!("no robot given"))?;
     let grid = Grid::new(num_rows, num_columns, cells);
-    let warehouse = Warehouse::new(grid, robot_pos);
+    let warehouse = Warehouse::new(grid, boxes, robot_pos);
 
     let mut movements = Vec::new();
     for line in movement_lines {
-        for ch in line.chars() {
+        for ch in line.text.chars() {
             match ch {
                 '^' => movements.push(Direction::North),
                 '>' => movements.push(Direction::East),
                 'v' => movements.push(Direction::South),
                 '<' => movements.push(Direction::West),
-                _ => return Err(Error::msg("invalid movement")),
+                _ => return Err(line.error(format!("invalid movement {ch:?}"))),
             }
         }
-    }    
+    }
 
     Ok((warehouse, movements))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SMALL: &str = "\
+#######
+#...#.#
+#.....#
+#..OO@#
+#..O..#
+#.....#
+#######
+
+<vv<<^^<<^^
+";
+
+    #[test]
+    fn test_wide_push_and_undo() {
+        let (warehouse, movements) = read_warehouse(BufReader::new(SMALL.as_bytes())).unwrap();
+        let start = warehouse.widen(2);
+        let mut warehouse = start.clone();
+
+        let log = warehouse.move_robot(&movements);
+        assert_eq!(warehouse.gps_box_sum(), 105 + 207 + 306);
+
+        for mv in log.iter().rev() {
+            warehouse.undo(mv);
+        }
+        assert_eq!(warehouse.render(), start.render());
+    }
+
+    #[test]
+    fn test_replay() {
+        let (warehouse, movements) = read_warehouse(BufReader::new(SMALL.as_bytes())).unwrap();
+        let warehouse = warehouse.widen(2);
+        let mut replay = Replay::new(warehouse.clone(), &movements);
+
+        let mut expected = warehouse.clone();
+        for step in 0..=movements.len() {
+            if step > 0 {
+                expected.move_robot_one_step(&movements[step - 1]);
+            }
+            assert_eq!(replay.seek(step).render(), expected.render());
+        }
+        assert_eq!(replay.seek(0).render(), warehouse.render());
+        assert_eq!(replay.box_move_frames(), vec![1, 6, 11]);
+    }
+}
@@ -226,26 +226,40 @@ impl Interpreter {
 }
 
 fn read_evaluation_setup(reader: impl BufRead) -> Result<(Interpreter, Program)> {
-    let lines = read_lines(reader);
+    let (register_lines, program_lines) = read_two_sections(reader)?;
     let mut registers = [0_usize; 3];
+    if register_lines.len() != registers.len() {
+        return Err(register_lines[0].error("expected the registers A, B and C"));
+    }
 
-    for (i, line) in lines[..3].iter().enumerate() {
-        let parts = line.split(':').collect::<Vec<&str>>();
-        if parts.len() != 2 {
-            return Err(anyhow!("invalid program line"));
-        }
-        registers[i] = parts[1].trim().parse::<usize>()?;
+    for (i, line) in register_lines.iter().enumerate() {
+        let (_, value) = line.text
+            .split_once(':')
+            .ok_or_else(|| line.error("invalid register line"))?;
+        registers[i] = value
+            .trim()
+            .parse::<usize>()
+            .map_err(|e| line.error(e))?;
     }
 
     let [a, b, c] = registers;
     let interpreter = Interpreter::new(a, b, c);
 
-    let segments = &lines[4].split(':').collect::<Vec<&str>>();
-    let values = segments[1]
+    let [program_line] = program_lines.as_slice() else {
+        return Err(program_lines[1].error("the program must be given in one line"));
+    };
+    let (_, program_values) = program_line.text
+        .split_once(':')
+        .ok_or_else(|| program_line.error("invalid program line"))?;
+    let values = program_values
         .trim()
         .split(',')
-        .map(|s| s.parse::<usize>().unwrap())
-        .collect::<Vec<_>>();
+        .map(|s| s.parse::<usize>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| program_line.error(e))?;
+    if values.len() % 2 != 0 {
+        return Err(program_line.error("every operator needs an operand"));
+    }
 
     let mut program = Vec::new();
     for chunk in values.chunks(2) {
@@ -261,7 +275,7 @@ fn read_evaluation_setup(reader: impl BufRead) -> Result<(Interpreter, Program)>
             5 => Out(*operand),
             6 => Bdv(*operand),
             7 => Cdv(*operand),
-            _ => return Err(anyhow!("invalid operator")),
+            _ => return Err(program_line.error(format!("invalid operator {op}"))),
         };
         program.push(instruction);
     }
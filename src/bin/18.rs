@@ -38,73 +38,80 @@ const TEST: &str = "\
 1,6
 2,0
 ";
+const TEST_CONFIG: Config = Config { num_rows: 7, num_cols: 7, time: 12 };
+const INPUT_CONFIG: Config = Config { num_rows: 71, num_cols: 71, time: 1024 };
 
 fn main() -> Result<()> {
     start_day(DAY);
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let config = INPUT_CONFIG.with_args(&args)?;
 
     //region Part 1
     println!("=== Part 1 ===");
 
-    fn part1<R: BufRead>(
-        reader: R,
-        num_rows: usize,
-        num_cols: usize,
-        time: usize,
-    ) -> Result<usize> {
-        let memory = read_memory(reader, num_rows, num_cols);
+    fn part1<R: BufRead>(reader: R, config: &Config) -> Result<usize> {
+        let memory = read_memory(reader, config.num_rows, config.num_cols)?;
         let start = State::new(Position::new(0, 0));
-        let goal = Position::new((num_rows - 1) as i32, (num_cols - 1) as i32);
+        let goal = Position::new((config.num_rows - 1) as i32, (config.num_cols - 1) as i32);
 
         memory
-            .dijkstra(start, &goal, time)
+            .dijkstra(start, &goal, config.time)
             .ok_or(anyhow!("no solution"))
     }
 
-    assert_eq!(22, part1(BufReader::new(TEST.as_bytes()), 7, 7, 12)?);
+    assert_eq!(22, part1(BufReader::new(TEST.as_bytes()), &TEST_CONFIG)?);
 
     let input_file = BufReader::new(File::open(INPUT_FILE)?);
-    let result = time_snippet!(part1(input_file, 71, 71, 1024)?);
+    let result = time_snippet!(part1(input_file, &config)?);
     println!("Result = {}", result);
     //endregion
 
     //region Part 2
     println!("\n=== Part 2 ===");
 
-    fn part2<R: BufRead>(reader: R, num_rows: usize, num_cols: usize) -> Result<String> {
-        let memory = read_memory(reader, num_rows, num_cols);
-        let start = State::new(Position::new(0, 0));
-        let goal = Position::new((num_rows - 1) as i32, (num_cols - 1) as i32);
-        let mut time_min = 0;
-        let mut time_max = memory.max_time;
-
-        loop {
-            if time_max - time_min <= 1 {
-                let blocking_pos = memory.find_cell(time_max).unwrap();
-                return Ok(format!("{},{}", blocking_pos.col(), blocking_pos.row()));
-            }
+    fn part2<R: BufRead>(reader: R, config: &Config) -> Result<String> {
+        let memory = read_memory(reader, config.num_rows, config.num_cols)?;
+        let (_, blocking_pos) = memory
+            .find_first_blocking_byte()
+            .ok_or(anyhow!("the exit stays reachable"))?;
 
-            let time = (time_min + time_max) / 2;
-            match memory.dijkstra(start.clone(), &goal, time) {
-                Some(_) => {
-                    time_min = time;
-                }
-                None => {
-                    time_max = time;
-                }
-            }
-        }
+        Ok(to_coordinates(&blocking_pos))
     }
 
-    assert_eq!("6,1", part2(BufReader::new(TEST.as_bytes()), 7, 7)?);
+    assert_eq!("6,1", part2(BufReader::new(TEST.as_bytes()), &TEST_CONFIG)?);
 
     let input_file = BufReader::new(File::open(INPUT_FILE)?);
-    let result = time_snippet!(part2(input_file, 71, 71)?);
+    let result = time_snippet!(part2(input_file, &config)?);
     println!("Result = {}", result);
     //endregion
 
     Ok(())
 }
 
+/// Puzzle parameters, overridable with `--rows`, `--cols` and `--time`.
+#[derive(Debug, Clone)]
+struct Config {
+    num_rows: usize,
+    num_cols: usize,
+    time: usize,
+}
+
+impl Config {
+    fn with_args(self, args: &[String]) -> Result<Self> {
+        check_options(args, &["--rows", "--cols", "--time"], &[])?;
+        let config = Config {
+            num_rows: parse_option(args, "--rows")?.unwrap_or(self.num_rows),
+            num_cols: parse_option(args, "--cols")?.unwrap_or(self.num_cols),
+            time: parse_option(args, "--time")?.unwrap_or(self.time),
+        };
+        if config.num_rows == 0 || config.num_cols == 0 {
+            return Err(anyhow!("the memory needs at least one row and one column"));
+        }
+
+        Ok(config)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
 struct State {
     position: Position,
@@ -120,20 +127,102 @@ impl State {
 
         [North, South, East, West]
             .iter()
-            .map(|d| (State::new(self.position.make_step(&d)), 1))
+            .map(|d| (State::new(self.position.make_step(d)), 1))
             .collect::<Vec<_>>()
     }
 }
 
+#[derive(Debug, Clone)]
+struct UnionFind {
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(size: usize) -> Self {
+        Self { parents: (0..size).collect(), sizes: vec![1; size] }
+    }
+
+    fn find(&mut self, node: usize) -> usize {
+        let mut root = node;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+        let mut current = node;
+        while self.parents[current] != root {
+            let next = self.parents[current];
+            self.parents[current] = root;
+            current = next;
+        }
+
+        root
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        if self.sizes[a] < self.sizes[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parents[b] = a;
+        self.sizes[a] += self.sizes[b];
+    }
+
+    fn connected(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+}
+
 #[derive(Debug, Clone)]
 struct Memory {
     grid: Grid<usize>,
-    max_time: usize,
+    bytes: Vec<Position>,
 }
 
 impl Memory {
-    fn new(grid: Grid<usize>, max_time: usize) -> Self {
-        Self { grid, max_time }
+    fn new(grid: Grid<usize>, bytes: Vec<Position>) -> Self {
+        Self { grid, bytes }
+    }
+
+    /// Returns the time and position of the first byte that cuts the start off from
+    /// the exit. The path is cut as soon as the corrupted cells form a chain (including
+    /// diagonal neighbors) from the north or east border to the south or west border.
+    fn find_first_blocking_byte(&self) -> Option<(usize, Position)> {
+        let num_cells = (self.grid.num_rows * self.grid.num_cols) as usize;
+        let north_east = num_cells;
+        let south_west = num_cells + 1;
+        let mut union_find = UnionFind::new(num_cells + 2);
+        let index = |pos: &Position| (pos.row() * self.grid.num_cols + pos.col()) as usize;
+
+        for (t, pos) in self.bytes.iter().enumerate() {
+            let time = t + 1;
+            if pos.row() == 0 || pos.col() == self.grid.num_cols - 1 {
+                union_find.union(index(pos), north_east);
+            }
+            if pos.row() == self.grid.num_rows - 1 || pos.col() == 0 {
+                union_find.union(index(pos), south_west);
+            }
+            for (dr, dc) in (-1..=1).flat_map(|dr| (-1..=1).map(move |dc| (dr, dc))) {
+                let neighbor = Position::new(pos.row() + dr, pos.col() + dc);
+                if self.is_corrupted(&neighbor, time) {
+                    union_find.union(index(pos), index(&neighbor));
+                }
+            }
+
+            if union_find.connected(north_east, south_west) {
+                return Some((time, pos.clone()));
+            }
+        }
+
+        None
+    }
+
+    fn is_corrupted(&self, position: &Position, time: usize) -> bool {
+        self.grid
+            .value_at(position)
+            .is_some_and(|value| *value != 0 && *value <= time)
     }
 
     #[allow(dead_code)]
@@ -151,18 +240,6 @@ impl Memory {
         }
     }
 
-    fn find_cell(&self, time: usize) -> Option<Position> {
-        for y in 0..self.grid.num_rows {
-            for x in 0..self.grid.num_cols {
-                let value = self.grid.value_at(&Position::new(y, x)).unwrap();
-                if *value == time {
-                    return Some(Position::new(y, x));
-                }
-            }
-        }
-        None
-    }
-
     fn dijkstra(&self, start: State, goal: &Position, time: usize) -> Option<usize> {
         let mut priority_queue = BinaryHeap::new();
         let mut distances: HashMap<State, usize> = HashMap::new();
@@ -206,8 +283,7 @@ impl Memory {
             if !self.grid.is_valid_position(&st.position) {
                 continue;
             }
-            let value = self.grid.value_at(&st.position).unwrap();
-            if *value == 0 || *value > time {
+            if !self.is_corrupted(&st.position, time) {
                 ret.push((st, cost));
             }
         }
@@ -216,19 +292,37 @@ impl Memory {
     }
 }
 
-fn read_memory<R: BufRead>(reader: R, num_rows: usize, num_cols: usize) -> Memory {
+fn read_memory<R: BufRead>(reader: R, num_rows: usize, num_cols: usize) -> Result<Memory> {
     let mut cells = vec![vec![0_usize; num_cols]; num_rows];
-    let lines = read_lines(reader);
+    let mut bytes = Vec::new();
 
-    for (t, line) in lines.iter().enumerate() {
-        let coords = line
-            .split(',')
-            .map(|s| s.parse::<usize>().unwrap())
-            .collect::<Vec<_>>();
-        cells[coords[1]][coords[0]] = t + 1;
+    for (t, (x, y)) in read_and_transform(reader, parse_coordinates)?.into_iter().enumerate() {
+        if x >= num_cols || y >= num_rows {
+            let message = format!("byte {x},{y} falls outside the {num_cols}x{num_rows} memory");
+            return Err(InputError::new(t + 1, message).into());
+        }
+        // A byte falling onto an already corrupted cell does not change when it got corrupted
+        if cells[y][x] == 0 {
+            cells[y][x] = t + 1;
+        }
+        bytes.push(Position::new(y as i32, x as i32));
     }
 
     let grid = Grid::new(num_rows as i32, num_cols as i32, cells);
 
-    Memory::new(grid, lines.len())
+    Ok(Memory::new(grid, bytes))
+}
+
+fn parse_coordinates(line: &str) -> Result<(usize, usize)> {
+    let (x, y) = line
+        .trim_end()
+        .split_once(',')
+        .ok_or_else(|| anyhow!("expected coordinates like 5,4, found {line:?}"))?;
+
+    Ok((x.parse()?, y.parse()?))
+}
+
+/// Formats a position in the `x,y` notation of the puzzle.
+fn to_coordinates(position: &Position) -> String {
+    format!("{},{}", position.col(), position.row())
 }
@@ -1,11 +1,11 @@
 use adv_code_2024::*;
+use adv_code_2024::matching::Trie;
 use anyhow::*;
 use code_timing_macros::time_snippet;
 use const_format::concatcp;
-use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use trie_rs::{Trie, TrieBuilder};
 
 const DAY: &str = "19";
 const INPUT_FILE: &str = concatcp!("input/", DAY, ".txt");
@@ -30,14 +30,11 @@ fn main() -> Result<()> {
     println!("=== Part 1 ===");
 
     fn part1<R: BufRead>(reader: R) -> Result<usize> {
-        let problem = read_problem(reader);
-        let mut cache = HashMap::new();
+        let problem = read_problem(reader)?;
 
-        let count = problem
-            .designs
-            .iter()
-            .filter(|design| problem.can_create_design(design, &mut cache))
-            .count();
+        let count = parallel::par_count(
+            &problem.designs,
+            |design| problem.decompose(design).count() > 0);
 
         Ok(count)
     }
@@ -53,16 +50,22 @@ fn main() -> Result<()> {
     println!("\n=== Part 2 ===");
 
     fn part2<R: BufRead>(reader: R) -> Result<usize> {
-        let problem = read_problem(reader);
-        let mut cache = HashMap::new();
+        let problem = read_problem(reader)?;
 
-        let count = problem
-            .designs
-            .iter()
-            .map(|design| problem.count_possible_designs(design, &mut cache))
-            .sum();
+        let decompositions = problem.decompose_all();
+        let count = decompositions.iter().map(Decomposition::count).sum::<u128>();
 
-        Ok(count)
+        if let Some(decomposition) = decompositions.first() {
+            println!("{}: shortest {:?}, longest {:?}",
+                     decomposition.design, decomposition.shortest(), decomposition.longest());
+            for arrangement in decomposition.arrangements().take(3) {
+                println!("  {}", arrangement.join(" "));
+            }
+        }
+        let unused = problem.unused_patterns(&decompositions);
+        println!("{} of {} patterns are never used", unused.len(), problem.patterns.len());
+
+        Ok(usize::try_from(count)?)
     }
 
     assert_eq!(16, part2(BufReader::new(TEST.as_bytes()))?);
@@ -76,85 +79,233 @@ fn main() -> Result<()> {
 }
 
 struct Problem {
-    patterns_trie: Trie<u8>,
+    patterns: Vec<String>,
+    patterns_trie: Trie,
     designs: Vec<String>,
 }
 
 impl Problem {
     fn new(patterns: Vec<String>, designs: Vec<String>) -> Self {
-        let mut builder = TrieBuilder::new();
-
-        for pattern in &patterns {
-            builder.push(pattern);
-        }
-
         Self {
-            patterns_trie: builder.build(),
+            patterns_trie: Trie::new(&patterns),
+            patterns,
             designs,
         }
     }
 
-    fn count_possible_designs(&self, design: &str, cache: &mut HashMap<String, usize>) -> usize {
-        if cache.contains_key(design) {
-            return cache[design];
+    fn decompose<'a>(&self, design: &'a str) -> Decomposition<'a> {
+        let bytes = design.as_bytes();
+        let mut matches = vec![Vec::new(); bytes.len() + 1];
+        let mut ways = vec![0_u128; bytes.len() + 1];
+        ways[bytes.len()] = 1;
+
+        for offset in (0..bytes.len()).rev() {
+            // Keep only the patterns after which the rest of the design can be completed
+            matches[offset] = self
+                .patterns_trie
+                .prefix_matches(&bytes[offset..])
+                .filter(|len| ways[offset + len] > 0)
+                .collect();
+            ways[offset] = matches[offset]
+                .iter()
+                .map(|len| ways[offset + len])
+                .sum();
         }
 
-        if design.is_empty() {
-            return 1;
-        }
+        Decomposition { design, matches, ways }
+    }
+
+    fn decompose_all(&self) -> Vec<Decomposition<'_>> {
+        let designs = self.designs.iter().collect::<Vec<_>>();
+        parallel::par_map(&designs, |design| self.decompose(design))
+    }
 
-        let patterns = self
-            .patterns_trie
-            .common_prefix_search(design)
-            .collect::<Vec<String>>();
+    /// Patterns that do not appear in any arrangement of the decomposed designs.
+    fn unused_patterns(&self, decompositions: &[Decomposition]) -> Vec<&str> {
+        let used = decompositions
+            .iter()
+            .flat_map(Decomposition::used_patterns)
+            .collect::<HashSet<_>>();
 
-        let count = patterns
+        self.patterns
             .iter()
-            .map(|p| self.count_possible_designs(&design[p.len()..], cache))
-            .sum();
+            .filter(|pattern| !used.contains(pattern.as_str()))
+            .map(|pattern| pattern.as_str())
+            .collect()
+    }
+}
+
+/// All ways to build one design, as a DP over byte offsets. `matches[offset]` holds
+/// the lengths of the patterns starting at `offset` that lead to a complete design and
+/// `ways[offset]` the number of arrangements of the rest of the design.
+#[derive(Debug)]
+struct Decomposition<'a> {
+    design: &'a str,
+    matches: Vec<Vec<usize>>,
+    ways: Vec<u128>,
+}
+
+impl<'a> Decomposition<'a> {
+    fn count(&self) -> u128 {
+        self.ways[0]
+    }
 
-        cache.insert(design.to_string(), count);
-        count
+    fn shortest(&self) -> Option<Vec<&'a str>> {
+        self.arrangement_by(|candidate, best| candidate < best)
     }
 
-    fn can_create_design(&self, design: &str, cache: &mut HashMap<String, bool>) -> bool {
-        if cache.contains_key(design) {
-            return cache[design];
+    fn longest(&self) -> Option<Vec<&'a str>> {
+        self.arrangement_by(|candidate, best| candidate > best)
+    }
+
+    /// Picks the arrangement whose number of patterns is preferred by `is_better`.
+    fn arrangement_by(&self, is_better: impl Fn(usize, usize) -> bool) -> Option<Vec<&'a str>> {
+        if self.count() == 0 {
+            return None;
+        }
+
+        let len = self.design.len();
+        let mut num_pieces = vec![0; len + 1];
+        let mut choice = vec![0; len + 1];
+        for offset in (0..len).rev() {
+            for (idx, pattern_len) in self.matches[offset].iter().enumerate() {
+                let candidate = num_pieces[offset + pattern_len] + 1;
+                if idx == 0 || is_better(candidate, num_pieces[offset]) {
+                    num_pieces[offset] = candidate;
+                    choice[offset] = *pattern_len;
+                }
+            }
         }
 
-        if design.is_empty() {
-            return true;
+        let mut ret = Vec::new();
+        let mut offset = 0;
+        while offset < len {
+            ret.push(&self.design[offset..offset + choice[offset]]);
+            offset += choice[offset];
         }
 
-        let patterns = self
-            .patterns_trie
-            .common_prefix_search(design)
-            .collect::<Vec<String>>();
+        Some(ret)
+    }
 
-        for pattern in patterns {
-            if self.can_create_design(&design[pattern.len()..], cache) {
-                cache.insert(design.to_string(), true);
-                return true;
+    fn arrangements(&self) -> Arrangements<'_, 'a> {
+        Arrangements { decomposition: self, stack: Vec::new(), started: false }
+    }
+
+    /// Patterns that appear in at least one arrangement.
+    fn used_patterns(&self) -> HashSet<&'a str> {
+        let len = self.design.len();
+        let mut reachable = vec![false; len + 1];
+        reachable[0] = self.count() > 0;
+        let mut ret = HashSet::new();
+
+        for offset in 0..len {
+            if !reachable[offset] {
+                continue;
+            }
+            for pattern_len in &self.matches[offset] {
+                reachable[offset + pattern_len] = true;
+                ret.insert(&self.design[offset..offset + pattern_len]);
             }
         }
 
-        cache.insert(design.to_string(), false);
-        false
+        ret
     }
 }
 
-fn read_problem(reader: impl BufRead) -> Problem {
-    let mut designs = Vec::new();
+/// Lazy depth-first enumeration of all arrangements of a design. Every match left in
+/// the decomposition leads to a complete design, so no branch is a dead end.
+struct Arrangements<'d, 'a> {
+    decomposition: &'d Decomposition<'a>,
+    stack: Vec<(usize, usize)>,
+    started: bool,
+}
+
+impl<'a> Iterator for Arrangements<'_, 'a> {
+    type Item = Vec<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let matches = &self.decomposition.matches;
+
+        if self.started {
+            // Advance the deepest choice that still has an alternative
+            loop {
+                let (offset, choice) = self.stack.pop()?;
+                if choice + 1 < matches[offset].len() {
+                    self.stack.push((offset, choice + 1));
+                    break;
+                }
+            }
+        } else {
+            self.started = true;
+            if self.decomposition.count() == 0 {
+                return None;
+            }
+        }
+
+        let mut offset = self.stack
+            .last()
+            .map(|(offset, choice)| offset + matches[*offset][*choice])
+            .unwrap_or(0);
+        while offset < self.decomposition.design.len() {
+            self.stack.push((offset, 0));
+            offset += matches[offset][0];
+        }
 
-    let lines = read_lines(reader);
-    let patterns = lines[0]
-        .split(", ")
-        .map(|s| s.to_string())
+        let design = self.decomposition.design;
+        let arrangement = self.stack
+            .iter()
+            .map(|(offset, choice)| &design[*offset..offset + matches[*offset][*choice]])
+            .collect();
+
+        Some(arrangement)
+    }
+}
+
+fn read_problem(reader: impl BufRead) -> Result<Problem> {
+    let (pattern_lines, design_lines) = read_two_sections(reader)?;
+    let [pattern_line] = pattern_lines.as_slice() else {
+        return Err(pattern_lines[1].error("the towel patterns must be given in one line"));
+    };
+    let patterns = pattern_line.text
+        .split(',')
+        .map(|s| s.trim().to_string())
         .collect::<Vec<_>>();
+    let designs = design_lines
+        .into_iter()
+        .map(|line| line.text)
+        .collect();
+
+    Ok(Problem::new(patterns, designs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decompose() {
+        let problem = read_problem(BufReader::new(TEST.as_bytes())).unwrap();
 
-    for line in &lines[2..] {
-        designs.push(line.to_string());
+        let decomposition = problem.decompose("gbbr");
+        assert_eq!(decomposition.count(), 4);
+        assert_eq!(decomposition.shortest(), Some(vec!["gb", "br"]));
+        assert_eq!(decomposition.longest(), Some(vec!["g", "b", "b", "r"]));
+
+        let arrangements = decomposition.arrangements().collect::<HashSet<_>>();
+        assert_eq!(arrangements.len(), 4);
+        assert!(arrangements.contains(&vec!["g", "b", "br"]));
+        assert!(arrangements.contains(&vec!["gb", "b", "r"]));
+
+        let decomposition = problem.decompose("ubwu");
+        assert_eq!(decomposition.count(), 0);
+        assert_eq!(decomposition.shortest(), None);
+        assert_eq!(decomposition.arrangements().next(), None);
     }
 
-    Problem::new(patterns, designs)
+    #[test]
+    fn test_unused_patterns() {
+        let patterns = ["r", "wr", "b", "g", "bwu", "rb", "gb", "br"].map(String::from).to_vec();
+        let problem = Problem::new(patterns, vec!["brwrr".to_string(), "bggr".to_string()]);
+        assert_eq!(problem.unused_patterns(&problem.decompose_all()), ["bwu", "rb", "gb"]);
+    }
 }
@@ -1,10 +1,8 @@
-use std::collections::HashMap;
 use anyhow::*;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use code_timing_macros::time_snippet;
 use const_format::concatcp;
-use itertools::Itertools;
 use adv_code_2024::*;
 
 const DAY: &str = "22";
@@ -29,10 +27,11 @@ fn main() -> Result<()> {
             .map(|s| s.parse::<Secret>().unwrap())
             .collect::<Vec<_>>();
         
-        let answer = secrets
-            .iter()
-            .map(|secret| calc_nth_secret(*secret, 2000))
-            .sum::<Secret>();
+        let answer = parallel::par_map_reduce(
+            &secrets,
+            |secret| calc_nth_secret(*secret, 2000),
+            0,
+            |sum, secret| sum + secret);
       
         Ok(answer as usize)
     }
@@ -53,7 +52,7 @@ fn main() -> Result<()> {
             .map(|s| s.parse::<Secret>().unwrap())
             .collect::<Vec<_>>();
         
-        let answer = find_best_total_price(&secrets, 2000);
+        let (_, answer) = find_best_total_price(&secrets, 2000);
         Ok(answer as usize)
     }
     
@@ -70,77 +69,76 @@ fn main() -> Result<()> {
 type Secret = u64;
 type Sequence = [i32; 4];
 
-fn find_best_total_price(secrets: &[Secret], n: usize) -> Secret {
-    let sequences = vec![-9..=9;4]
-        .into_iter()
-        .multi_cartesian_product()
-        .collect_vec();
-    
-    let price_maps = all_prices_per_change_sequence(secrets, n);
-    
-    let mut prices = vec![];
-    for sequence in sequences {
-        let change_sequence = [
-            sequence[0],
-            sequence[1],
-            sequence[2],
-            sequence[3]];
-        prices.push(calc_total_price(&price_maps, &change_sequence));
-    }
-    
-    *prices.iter().max().unwrap()
-}
+const NUM_CHANGES: usize = 19;
+const NUM_SEQUENCES: usize = NUM_CHANGES * NUM_CHANGES * NUM_CHANGES * NUM_CHANGES;
 
-fn calc_total_price(price_maps: &[HashMap<Sequence, Secret>], change_sequence: &Sequence) -> Secret {
-    price_maps
-        .iter()
-        .flat_map(|price_map| find_price(price_map, change_sequence))
-        .sum()
+struct MonkeyMarket {
+    totals: Vec<Secret>,
+    seen: Vec<u32>,
+    num_buyers: u32,
 }
 
-fn find_price(price_map: &HashMap<Sequence, Secret>, change_sequence: &Sequence) -> Option<Secret> {
-    price_map.get(change_sequence).copied()
-}
+impl MonkeyMarket {
+    fn new() -> Self {
+        Self {
+            totals: vec![0; NUM_SEQUENCES],
+            seen: vec![0; NUM_SEQUENCES],
+            num_buyers: 0,
+        }
+    }
 
-fn all_prices_per_change_sequence(secrets: &[Secret], n: usize) -> Vec<HashMap<Sequence, Secret>> {
-    calc_all_values_and_changes(secrets, n)
-        .iter()
-        .map(|(secrets, changes)| prices_per_change_sequence(secrets, changes))
-        .collect()
-}
+    fn add_buyer(&mut self, secret: Secret, n: usize) {
+        self.num_buyers += 1;
+        let stamp = self.num_buyers;
+        let mut index = 0;
+
+        for (i, (price, change)) in price_changes(secret, n).enumerate() {
+            index = (index * NUM_CHANGES + (change + 9) as usize) % NUM_SEQUENCES;
+            if i < 3 || self.seen[index] == stamp {
+                continue;
+            }
+            self.seen[index] = stamp;
+            self.totals[index] += price;
+        }
+    }
 
-fn calc_all_values_and_changes(secrets: &[Secret], n: usize) -> Vec<(Vec<Secret>, Vec<i32>)> {
-    secrets
-        .iter()
-        .map(|secret| calc_values_and_changes(*secret, n))
-        .collect::<Vec<_>>()
+    fn best(&self) -> Option<(Sequence, Secret)> {
+        self.totals
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, total)| **total)
+            .map(|(index, total)| (decode_sequence(index), *total))
+    }
 }
 
-fn prices_per_change_sequence(secrets: &[Secret], changes: &[i32]) -> HashMap<Sequence, Secret> {
-    let mut ret = HashMap::new();
-    for (i, sequence) in changes.windows(4).enumerate() {
-        let price = secrets[i + 3];
-        let key = [sequence[0], sequence[1], sequence[2], sequence[3]];
-        ret.entry(key).or_insert(price);
+fn decode_sequence(index: usize) -> Sequence {
+    let mut ret = [0; 4];
+    let mut rest = index;
+    for change in ret.iter_mut().rev() {
+        *change = (rest % NUM_CHANGES) as i32 - 9;
+        rest /= NUM_CHANGES;
     }
 
     ret
 }
 
-fn calc_values_and_changes(secret: Secret, n: usize) -> (Vec<Secret>, Vec<i32>) {
-    let mut secrets = vec![secret % 10];
-    let mut cur = secret;
-    for _ in 0..n {
-        cur = next_secret(cur);
-        secrets.push(cur % 10);
+fn find_best_total_price(secrets: &[Secret], n: usize) -> (Sequence, Secret) {
+    let mut market = MonkeyMarket::new();
+    for secret in secrets {
+        market.add_buyer(*secret, n);
     }
 
-    let changes = secrets
-        .windows(2)
-        .map(|w| w[1] as i32 - w[0] as i32)   
-        .collect::<Vec<_>>();
-    
-    (secrets[1..].to_vec(), changes)
+    market.best().unwrap()
+}
+
+fn price_changes(secret: Secret, n: usize) -> impl Iterator<Item = (Secret, i32)> {
+    let mut cur = secret;
+    (0..n).map(move |_| {
+        let prev_price = cur % 10;
+        cur = next_secret(cur);
+        let price = cur % 10;
+        (price, price as i32 - prev_price as i32)
+    })
 }
 
 fn calc_nth_secret(secret: Secret, n: usize) -> Secret {
@@ -187,14 +185,16 @@ mod tests {
     #[test]
     fn test_calc_nth_changes() {   
         let secret = 123;
-        let changes = calc_values_and_changes(secret, 6).1.to_vec();
+        let changes = price_changes(secret, 6)
+            .map(|(_, change)| change)
+            .collect::<Vec<_>>();
         assert_eq!(changes, [-3, 6, -1, -1, 0, 2]);
     }
     
     #[test]
     fn test_find_best_total_price() {
         let secrets = [1, 2, 3, 2024];
-        assert_eq!(find_best_total_price(&secrets, 2000), 23);
+        assert_eq!(find_best_total_price(&secrets, 2000), ([-2, 1, -1, 3], 23));
     }
     
 }
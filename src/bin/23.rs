@@ -1,8 +1,6 @@
-use std::collections::{HashMap, HashSet};
-use std::fmt::{Display, Formatter};
+use std::collections::HashMap;
 use anyhow::*;
 use std::fs::File;
-use std::hash::{Hash, Hasher};
 use std::io::{BufRead, BufReader};
 use code_timing_macros::time_snippet;
 use const_format::concatcp;
@@ -55,18 +53,11 @@ fn main() -> Result<()> {
 
     fn part1<R: BufRead>(reader: R) -> Result<usize> {
         let network = read_network(reader)?;
-        let groups = find_groups_of_three(&network)
+        let groups = network.find_groups_of_three()
             .into_iter()
-            .filter(at_least_one_computer_starts_with_t)
+            .filter(|group| at_least_one_computer_starts_with_t(&network, group))
             .collect::<Vec<_>>();
 
-        /*
-        for group in &groups {
-            println!("{},{},{}", group.0, group.1, group.2);
-        }
-
-        */
-
         let answer = groups.len();
         Ok(answer)
     }
@@ -83,20 +74,10 @@ fn main() -> Result<()> {
     //
     fn part2<R: BufRead>(reader: R) -> Result<String> { 
         let network = read_network(reader)?;    
-        let groups = find_all_connected(&network);
-        let mut max_size = 0;
-        let mut max_group_opt: Option<Group> = None;
+        let max_group = network.find_max_clique()
+            .ok_or_else(|| anyhow!("no group found"))?;
         
-        for group in groups {
-            if group.get_size() > max_size {
-                max_size = group.get_size();
-                max_group_opt = Some(group);
-            }
-        }
-        
-        let max_group = max_group_opt.ok_or_else(|| anyhow!("no group found"))?;
-        
-        Ok(format!("{max_group}"))
+        Ok(network.format_group(&max_group))
     }
 
     assert_eq!("co,de,ka,ta", part2(BufReader::new(TEST.as_bytes()))?);
@@ -109,145 +90,231 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-type Network = HashMap<String, HashSet<String>>;
-type Group3 = (String, String, String);
+type NodeId = u16;
 
-#[derive(Debug, Eq, PartialEq, Clone)]
-struct Group {
-    members: HashSet<String>,
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+struct BitSet {
+    words: Vec<u64>,
 }
 
-impl Group {
-    fn new() -> Self {
-        Group{members: HashSet::new()}
+impl BitSet {
+    fn new(capacity: usize) -> Self {
+        BitSet { words: vec![0; capacity.div_ceil(64)] }
+    }
+
+    fn insert(&mut self, id: NodeId) {
+        self.words[id as usize / 64] |= 1 << (id % 64);
     }
 
-    fn add_member(&mut self, member: &str) {
-        self.members.insert(member.to_string());
+    fn remove(&mut self, id: NodeId) {
+        self.words[id as usize / 64] &= !(1 << (id % 64));
     }
 
-    fn contains_member(&self, member: &str) -> bool {
-        self.members.contains(member)
+    fn len(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
     }
 
     fn is_empty(&self) -> bool {
-        self.members.is_empty()
+        self.words.iter().all(|w| *w == 0)
     }
-    
-    fn get_size(&self) -> usize {
-        self.members.len()
+
+    fn intersection(&self, other: &BitSet) -> BitSet {
+        let words = self.words
+            .iter()
+            .zip(&other.words)
+            .map(|(a, b)| a & b)
+            .collect();
+        BitSet { words }
     }
-}
 
-impl Hash for Group {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        let members = &self.members
+    fn intersection_len(&self, other: &BitSet) -> usize {
+        self.words
             .iter()
-            .cloned()
-            .sorted()
-            .collect::<Vec<_>>()
-            .join(",");
-        members.hash(state);
+            .zip(&other.words)
+            .map(|(a, b)| (a & b).count_ones() as usize)
+            .sum()
     }
-}
 
-impl Display for Group {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let members = &self.members
+    fn difference(&self, other: &BitSet) -> BitSet {
+        let words = self.words
             .iter()
-            .cloned()
-            .sorted()
-            .collect::<Vec<_>>()
-            .join(",");
-        write!(f, "{members}")
+            .zip(&other.words)
+            .map(|(a, b)| a & !b)
+            .collect();
+        BitSet { words }
     }
+
+    fn union(&self, other: &BitSet) -> BitSet {
+        let words = self.words
+            .iter()
+            .zip(&other.words)
+            .map(|(a, b)| a | b)
+            .collect();
+        BitSet { words }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.words
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, word)| {
+                let mut word = *word;
+                std::iter::from_fn(move || {
+                    if word == 0 {
+                        return None;
+                    }
+                    let bit = word.trailing_zeros();
+                    word &= word - 1;
+                    Some((idx * 64 + bit as usize) as NodeId)
+                })
+            })
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+struct Group {
+    members: BitSet,
 }
 
-fn find_all_connected(network: &Network) -> HashSet<Group> {
-    let mut ret = HashSet::new();
-    for computer in network.keys() {
-        let mut group = Group::new();
-        find_connected(network, computer, &mut group);
-        if !group.is_empty() {
-            ret.insert(group);
+#[derive(Debug)]
+struct Network {
+    names: Vec<String>,
+    adjacency: Vec<BitSet>,
+}
+
+impl Network {
+    fn new(names: Vec<String>, connections: &[(NodeId, NodeId)]) -> Self {
+        let mut adjacency = vec![BitSet::new(names.len()); names.len()];
+        for (a, b) in connections {
+            adjacency[*a as usize].insert(*b);
+            adjacency[*b as usize].insert(*a);
         }
+        Network { names, adjacency }
     }
 
-    ret
-}
+    fn get_name(&self, id: NodeId) -> &str {
+        &self.names[id as usize]
+    }
 
-fn find_connected(network: &Network, computer: &str, group: &mut Group) {
-    if group.contains_member(computer) {
-        return;
+    fn get_connected(&self, id: NodeId) -> &BitSet {
+        &self.adjacency[id as usize]
     }
-    if let Some(connected) = network.get(computer) {
-        let members = group.members.clone().into_iter().collect::<Vec<_>>();
-        if members.iter().all(|c| connected.contains(c)) {
-            group.add_member(computer);
-        } else {
-            return;
-        }
-        for partner in connected {
-            find_connected(network, partner, group);
-        }
+
+    fn node_ids(&self) -> impl Iterator<Item = NodeId> {
+        0..self.names.len() as NodeId
     }
-}
 
-fn at_least_one_computer_starts_with_t(group: &Group3) -> bool {
-    [&group.0, &group.1, &group.2]
-        .iter()
-        .any(|s| s.starts_with("t"))
-}
+    fn format_group(&self, group: &Group) -> String {
+        group.members
+            .iter()
+            .map(|id| self.get_name(id))
+            .sorted()
+            .join(",")
+    }
 
-fn find_groups_of_three(network: &Network) -> Vec<Group3> {
-    let mut groups = HashSet::new();
-    for (computer, others) in network {
-        for other in others {
-            if other == computer {
-                continue;
-            }
-            if let Some(connections) = network.get(other) {
-                for connected_partner in connections {
-                    if connected_partner == computer || connected_partner == other {
-                        continue;
-                    }
-                    if let Some(partner_connections) = network.get(connected_partner) {
-                        if !partner_connections.contains(computer) {
-                            continue;
-                        }
-                    } else {
-                        continue;
-                    }
-                    let mut elements = [
-                        computer.clone(),
-                        other.clone(),
-                        connected_partner.clone()];
-                    elements.sort();
-                    let group = (elements[0].clone(), elements[1].clone(), elements[2].clone());
-                    groups.insert(group);
+    fn find_groups_of_three(&self) -> Vec<[NodeId; 3]> {
+        let mut ret = Vec::new();
+        for a in self.node_ids() {
+            let connected_a = self.get_connected(a);
+            for b in connected_a.iter().filter(|b| *b > a) {
+                let common = connected_a.intersection(self.get_connected(b));
+                for c in common.iter().filter(|c| *c > b) {
+                    ret.push([a, b, c]);
                 }
             }
         }
+
+        ret
+    }
+
+    fn find_max_clique(&self) -> Option<Group> {
+        let mut candidates = BitSet::new(self.names.len());
+        for id in self.node_ids() {
+            candidates.insert(id);
+        }
+        let mut best = BitSet::new(self.names.len());
+        self.bron_kerbosch(
+            BitSet::new(self.names.len()),
+            candidates,
+            BitSet::new(self.names.len()),
+            &mut best);
+
+        if best.is_empty() {
+            None
+        } else {
+            Some(Group { members: best })
+        }
     }
 
-    let mut ret = Vec::from_iter(groups.clone());
-    ret.sort();
+    fn bron_kerbosch(&self, clique: BitSet, mut candidates: BitSet, mut excluded: BitSet, best: &mut BitSet) {
+        if candidates.is_empty() {
+            if excluded.is_empty() && clique.len() > best.len() {
+                *best = clique;
+            }
+            return;
+        }
+        if clique.len() + candidates.len() <= best.len() {
+            return;
+        }
 
-    ret
+        let pivot = candidates
+            .union(&excluded)
+            .iter()
+            .max_by_key(|id| candidates.intersection_len(self.get_connected(*id)))
+            .unwrap();
+
+        for id in candidates.difference(self.get_connected(pivot)).iter() {
+            let connected = self.get_connected(id);
+            let mut new_clique = clique.clone();
+            new_clique.insert(id);
+            self.bron_kerbosch(
+                new_clique,
+                candidates.intersection(connected),
+                excluded.intersection(connected),
+                best);
+            candidates.remove(id);
+            excluded.insert(id);
+        }
+    }
+}
+
+fn at_least_one_computer_starts_with_t(network: &Network, group: &[NodeId; 3]) -> bool {
+    group
+        .iter()
+        .any(|id| network.get_name(*id).starts_with("t"))
 }
 
 fn read_network(reader: impl BufRead) -> Result<Network> {
-    let mut network: HashMap<String, HashSet<String>> = HashMap::new();
-    for line in read_lines(reader) {
+    let mut names: Vec<String> = Vec::new();
+    let mut ids: HashMap<String, NodeId> = HashMap::new();
+    let mut connections = Vec::new();
+
+    // Names are two lowercase letters, so there are at most 26 * 26 ids
+    let mut intern = |name: &str| -> Result<NodeId> {
+        if name.len() != 2 || !name.bytes().all(|b| b.is_ascii_lowercase()) {
+            return Err(anyhow!("computer names must be two lowercase letters, found {name:?}"));
+        }
+        if let Some(id) = ids.get(name) {
+            return Ok(*id);
+        }
+        let id = names.len() as NodeId;
+        names.push(name.to_string());
+        ids.insert(name.to_string(), id);
+        Ok(id)
+    };
+
+    for (idx, line) in read_lines(reader).iter().enumerate() {
         let parts = line.split('-').collect::<Vec<&str>>();
         if parts.len() != 2 {
-            return Err(anyhow!("invalid input"));
+            return Err(InputError::new(idx + 1, format!("expected a connection like kh-tc, found {line:?}")).into());
+        }
+        let computer1 = intern(parts[0]).map_err(|e| InputError::from_error(idx + 1, e))?;
+        let computer2 = intern(parts[1]).map_err(|e| InputError::from_error(idx + 1, e))?;
+        if computer1 == computer2 {
+            return Err(InputError::new(idx + 1, format!("{} cannot be connected to itself", parts[0])).into());
         }
-        let computer1 = parts[0].to_string();
-        let computer2 = parts[1].to_string();
-        network.entry(computer1.clone()).or_default().insert(computer2.clone());
-        network.entry(computer2.clone()).or_default().insert(computer1.clone());
+        connections.push((computer1, computer2));
     }
 
-    Ok(network)
+    Ok(Network::new(names, &connections))
 }
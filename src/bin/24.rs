@@ -7,6 +7,7 @@ use code_timing_macros::time_snippet;
 use const_format::concatcp;
 use itertools::Itertools;
 use adv_code_2024::*;
+use adv_code_2024::line_pattern::{Fields, FromCaptures, LinePattern};
 use crate::WireState::{Off, On, Unknown};
 
 const DAY: &str = "24";
@@ -311,52 +312,51 @@ impl Wiring {
 }
 
 fn read_wiring(reader: impl BufRead) -> Result<Wiring> {
-    let mut wires = HashMap::new();
+    let wire_pattern = LinePattern::<InitialWire>::new(r"(?<wire>\w+): (?<state>[01])")?;
+    let gate_pattern = LinePattern::<Gate>::new(r"(?<in1>\w+) (?<gate>\w+) (?<in2>\w+) -> (?<out>\w+)")?;
+    let mut sections = Sections::new(reader);
+    let initial_wires = wire_pattern.parse_all(sections.expect_section("initial wire values")?)?;
+    let gate_list = gate_pattern.parse_all(sections.expect_section("gates")?)?;
+    sections.expect_end()?;
+
+    let mut wires = initial_wires
+        .into_iter()
+        .map(|initial| (initial.wire, WireData::new(initial.state)))
+        .collect::<HashMap<_, _>>();
     let mut gates = HashMap::new();
-    let mut first_section = true;
-    let mut next_gate_id = 1;
+    for (gate_id, gate) in (1..).zip(gate_list) {
+        update_wire_data(gate_id, &gate.inputs[0], &gate.inputs[1], &gate.output, &mut wires);
+        gates.insert(gate_id, gate);
+    }
 
-    for line in read_lines(reader) {
-        if line.is_empty() {
-            first_section = false;
-            continue;
-        }
+    Ok(Wiring::new(wires, gates))
+}
 
-        if first_section {
-            let segments = line.split(":").collect::<Vec<_>>();
-            let wire = segments[0].to_string();
-            let state = segments[1].trim();
-            let state = match state {
-                "1" => On,
-                "0" => Off,
-                _ => return Err(anyhow!("invalid wire state {}", state)),
-            };
-            wires.insert(wire, WireData::new(state));
-        } else {
-            let segments = line.split(" ").collect::<Vec<_>>();
-            if segments.len() != 5 {
-                return Err(anyhow!("invalid wire format"));
-            }
-            let in1 = segments[0].trim();
-            let in2 = segments[2].trim();
-            let out = segments[4].trim();
-            update_wire_data(next_gate_id, in1, in2, out, &mut wires);
-
-            let gate_type = match segments[1].trim() {
-                "AND" => GateType::And,
-                "OR" => GateType::Or,
-                "XOR" => GateType::Xor,
-                _ => return Err(anyhow!("invalid gate")),
-            };
-            gates.insert(next_gate_id, Gate::new(gate_type,
-                                                 vec![in1.to_string(), in2.to_string()],
-                                                 out.to_string()));
-            next_gate_id += 1;
-        }
-    }
+struct InitialWire {
+    wire: String,
+    state: WireState,
+}
 
+impl FromCaptures for InitialWire {
+    fn from_captures(fields: &Fields) -> Result<Self> {
+        let state = if fields.str("state")? == "1" { On } else { Off };
+        Ok(InitialWire { wire: fields.str("wire")?.to_string(), state })
+    }
+}
 
-    Ok(Wiring::new(wires, gates))
+impl FromCaptures for Gate {
+    fn from_captures(fields: &Fields) -> Result<Self> {
+        let gate_type = match fields.str("gate")? {
+            "AND" => GateType::And,
+            "OR" => GateType::Or,
+            "XOR" => GateType::Xor,
+            gate => return Err(anyhow!("invalid gate {}", gate)),
+        };
+
+        Ok(Gate::new(gate_type,
+                     vec![fields.str("in1")?.to_string(), fields.str("in2")?.to_string()],
+                     fields.str("out")?.to_string()))
+    }
 }
 
 fn update_wire_data(gate_id: GateId,
@@ -1,3 +1,4 @@
+use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
 #[derive(Debug, EnumIter, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
@@ -85,3 +86,154 @@ impl<T> Grid<T> {
         }
     }
 }
+
+/// Smallest rectangle containing a set of positions, including both corners.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoundingBox {
+    pub top_left: Position,
+    pub bottom_right: Position,
+}
+
+impl BoundingBox {
+    fn extend(&mut self, position: &Position) {
+        self.top_left = Position::new(
+            self.top_left.row().min(position.row()),
+            self.top_left.col().min(position.col()));
+        self.bottom_right = Position::new(
+            self.bottom_right.row().max(position.row()),
+            self.bottom_right.col().max(position.col()));
+    }
+}
+
+/// Statistics of a connected region of cells that hold the same value.
+#[derive(Debug, Clone)]
+pub struct RegionInfo<T> {
+    pub value: T,
+    pub area: usize,
+    pub perimeter: usize,
+    /// Number of corners, which equals the number of straight sides of the region
+    pub num_corners: usize,
+    pub bounding_box: BoundingBox,
+}
+
+#[derive(Debug, Clone)]
+pub struct Components<T> {
+    /// Region label of every cell, the label is the index into `regions`
+    pub labels: Grid<usize>,
+    pub regions: Vec<RegionInfo<T>>,
+}
+
+impl<T: Clone + PartialEq> Grid<T> {
+    /// Labels the connected components of cells with equal values (4-neighbourhood)
+    /// and computes area, perimeter, corners and bounding box of each of them.
+    pub fn connected_components(&self) -> Components<T> {
+        let labels = self.label_components();
+        let mut regions: Vec<RegionInfo<T>> = Vec::new();
+
+        for row in 0..self.num_rows {
+            for col in 0..self.num_cols {
+                let pos = Position::new(row, col);
+                let label = labels.cells[row as usize][col as usize];
+                let same_region = |p: &Position| labels.value_at(p) == Some(&label);
+
+                if label == regions.len() {
+                    regions.push(RegionInfo {
+                        value: self.cells[row as usize][col as usize].clone(),
+                        area: 0,
+                        perimeter: 0,
+                        num_corners: 0,
+                        bounding_box: BoundingBox { top_left: pos.clone(), bottom_right: pos.clone() },
+                    });
+                }
+                let region = &mut regions[label];
+                region.area += 1;
+                region.bounding_box.extend(&pos);
+
+                for dir in Direction::iter() {
+                    let neighbor = pos.make_step(&dir);
+                    if !same_region(&neighbor) {
+                        region.perimeter += 1;
+                    }
+
+                    // Every cell can contribute one corner per diagonal
+                    let side = pos.make_step(&dir.turn_right());
+                    let diagonal = neighbor.make_step(&dir.turn_right());
+                    let is_convex = !same_region(&neighbor) && !same_region(&side);
+                    let is_concave = same_region(&neighbor) && same_region(&side) && !same_region(&diagonal);
+                    if is_convex || is_concave {
+                        region.num_corners += 1;
+                    }
+                }
+            }
+        }
+
+        Components { labels, regions }
+    }
+
+    /// Assigns labels in row-major order of the first cell of each region.
+    fn label_components(&self) -> Grid<usize> {
+        const UNLABELED: usize = usize::MAX;
+        let mut labels = Grid::new(
+            self.num_rows,
+            self.num_cols,
+            vec![vec![UNLABELED; self.num_cols as usize]; self.num_rows as usize]);
+        let mut next_label = 0;
+
+        for row in 0..self.num_rows {
+            for col in 0..self.num_cols {
+                let start = Position::new(row, col);
+                if labels.value_at(&start) != Some(&UNLABELED) {
+                    continue;
+                }
+                let value = self.value_at(&start);
+                labels.set_value_at(&start, next_label);
+                let mut todo = vec![start];
+
+                while let Some(current) = todo.pop() {
+                    for dir in Direction::iter() {
+                        let neighbor = current.make_step(&dir);
+                        if labels.value_at(&neighbor) == Some(&UNLABELED) && self.value_at(&neighbor) == value {
+                            labels.set_value_at(&neighbor, next_label);
+                            todo.push(neighbor);
+                        }
+                    }
+                }
+                next_label += 1;
+            }
+        }
+
+        labels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connected_components() {
+        let cells = ["AAAA", "BBCD", "BBCC", "EEEC"]
+            .iter()
+            .map(|line| line.chars().collect())
+            .collect();
+        let grid = Grid::new(4, 4, cells);
+        let components = grid.connected_components();
+
+        let stats = components.regions
+            .iter()
+            .map(|r| (r.value, r.area, r.perimeter, r.num_corners))
+            .collect::<Vec<_>>();
+        assert_eq!(stats, vec![
+            ('A', 4, 10, 4),
+            ('B', 4, 8, 4),
+            ('C', 4, 10, 8),
+            ('D', 1, 4, 4),
+            ('E', 3, 8, 4),
+        ]);
+        assert_eq!(components.regions[2].bounding_box, BoundingBox {
+            top_left: Position::new(1, 2),
+            bottom_right: Position::new(3, 3),
+        });
+        assert_eq!(components.labels.value_at(&Position::new(3, 3)), Some(&2));
+    }
+}
@@ -1,5 +1,7 @@
-use std::io::BufRead;
-use anyhow::Result;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::str::FromStr;
+use anyhow::{anyhow, Result};
 
 pub fn start_day(day: &str) {
     println!("Advent of Code 2024 - Day {:0>2}", day);
@@ -16,20 +18,254 @@ pub fn read_lines(reader: impl BufRead) -> Vec<String> {
     ret
 }
 
+/// Transforms every line with `transformer`. A failing transformation is reported as
+/// an [`InputError`] with the line number that keeps the original error as its source.
 pub fn read_and_transform<T>(reader: impl BufRead, transformer: fn(&str) -> Result<T>) -> Result<Vec<T>> {
     let mut ret = Vec::new();
-    for line in reader.lines() {
+    for (idx, line) in reader.lines().enumerate() {
         let line = line?;
-        let value = transformer(&line)?;
+        let value = transformer(&line)
+            .map_err(|e| InputError::from_error(idx + 1, e))?;
         ret.push(value);
     }
     
     Ok(ret)
 }
 
+/// Error in a specific line of the puzzle input. It displays only the line number,
+/// what is wrong with the line is its source, so `{:#}` prints `line 3: <cause>`.
+#[derive(Debug)]
+pub struct InputError {
+    /// 1-based line number
+    pub line: usize,
+    source: anyhow::Error,
+}
+
+impl InputError {
+    pub fn new(line: usize, message: impl Into<String>) -> Self {
+        Self::from_error(line, anyhow!(message.into()))
+    }
+
+    pub fn from_error(line: usize, source: anyhow::Error) -> Self {
+        Self { line, source }
+    }
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}", self.line)
+    }
+}
+
+impl std::error::Error for InputError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+/// A line of the puzzle input with its 1-based line number. Trailing whitespace,
+/// including the `\r` of CRLF line endings, is removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub number: usize,
+    pub text: String,
+}
+
+impl Line {
+    pub fn error(&self, message: impl fmt::Display) -> anyhow::Error {
+        InputError::new(self.number, message.to_string()).into()
+    }
+
+    /// Parses the whole line, reporting failures with the line number.
+    pub fn parse<T>(&self) -> Result<T>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.text
+            .parse()
+            .map_err(|e| self.error(format!("cannot parse {:?}: {e}", self.text)))
+    }
+}
+
+/// Streams an input made of sections that are separated by blank lines. Leading,
+/// trailing and repeated blank lines do not produce empty sections.
+pub struct Sections<R> {
+    lines: io::Lines<R>,
+    line_number: usize,
+    /// First line of the current section, read ahead to detect its start
+    pending: Option<Line>,
+    in_section: bool,
+}
+
+impl<R: BufRead> Sections<R> {
+    pub fn new(reader: R) -> Self {
+        Self { lines: reader.lines(), line_number: 0, pending: None, in_section: false }
+    }
+
+    fn read_line(&mut self) -> Option<io::Result<Line>> {
+        let text = match self.lines.next()? {
+            Ok(text) => text,
+            Err(e) => return Some(Err(e)),
+        };
+        self.line_number += 1;
+
+        Some(Ok(Line { number: self.line_number, text: text.trim_end().to_string() }))
+    }
+
+    fn next_line_in_section(&mut self) -> Option<io::Result<Line>> {
+        if !self.in_section {
+            return None;
+        }
+        if let Some(line) = self.pending.take() {
+            return Some(Ok(line));
+        }
+        match self.read_line() {
+            Some(Ok(line)) if line.text.is_empty() => {
+                self.in_section = false;
+                None
+            }
+            None => {
+                self.in_section = false;
+                None
+            }
+            line => line,
+        }
+    }
+
+    /// Skips the rest of a section that was not read completely.
+    fn skip_section(&mut self) -> io::Result<()> {
+        while let Some(line) = self.next_line_in_section() {
+            line?;
+        }
+        Ok(())
+    }
+
+    pub fn next_section(&mut self) -> io::Result<Option<Section<'_, R>>> {
+        self.skip_section()?;
+        while let Some(line) = self.read_line() {
+            let line = line?;
+            if !line.text.is_empty() {
+                self.pending = Some(line);
+                self.in_section = true;
+                return Ok(Some(Section { sections: self }));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the next section or an error naming the missing `what`.
+    pub fn expect_section(&mut self, what: &str) -> Result<Section<'_, R>> {
+        self.skip_section()?;
+        let end = self.line_number + 1;
+        self.next_section()?.ok_or_else(|| {
+            InputError::new(end, format!("missing {what}, sections must be separated by a blank line")).into()
+        })
+    }
+
+    /// Fails if there is another section.
+    pub fn expect_end(&mut self) -> Result<()> {
+        match self.next_section()?.and_then(|mut section| section.next()) {
+            Some(line) => Err(line?.error("unexpected section")),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The lines of one section. Reading stops at the next blank line.
+pub struct Section<'a, R> {
+    sections: &'a mut Sections<R>,
+}
+
+impl<R: BufRead> Iterator for Section<'_, R> {
+    type Item = io::Result<Line>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.sections.next_line_in_section()
+    }
+}
+
+impl<R: BufRead> Section<'_, R> {
+    pub fn collect_lines(self) -> Result<Vec<Line>> {
+        Ok(self.collect::<io::Result<Vec<_>>>()?)
+    }
+
+    /// Transforms every line of the section. Errors are reported like in
+    /// [`read_and_transform`], with the line number in the whole input.
+    pub fn transform<T>(self, transformer: fn(&str) -> Result<T>) -> Result<Vec<T>> {
+        self.map(|line| {
+            let line = line?;
+            transformer(&line.text).map_err(|e| InputError::from_error(line.number, e).into())
+        })
+        .collect()
+    }
+}
+
+/// Reads all sections of the input.
+pub fn read_sections(reader: impl BufRead) -> Result<Vec<Vec<Line>>> {
+    let mut sections = Sections::new(reader);
+    let mut ret = Vec::new();
+    while let Some(section) = sections.next_section()? {
+        ret.push(section.collect_lines()?);
+    }
+
+    Ok(ret)
+}
+
+/// Reads an input that consists of exactly two sections separated by a blank line.
+pub fn read_two_sections(reader: impl BufRead) -> Result<(Vec<Line>, Vec<Line>)> {
+    let mut sections = Sections::new(reader);
+    let first = sections.expect_section("first section")?.collect_lines()?;
+    let second = sections.expect_section("second section")?.collect_lines()?;
+    sections.expect_end()?;
+
+    Ok((first, second))
+}
+
+/// Looks up the value following the command line option `name`, e.g. `--time 1024`.
+pub fn parse_option<T>(args: &[String], name: &str) -> Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let Some(idx) = args.iter().position(|arg| arg == name) else {
+        return Ok(None);
+    };
+    let value = args
+        .get(idx + 1)
+        .ok_or_else(|| anyhow!("{name} requires a value"))?;
+
+    Ok(Some(value.parse::<T>()?))
+}
+
+/// Whether the command line contains the flag `name`, e.g. `--pushes`.
+pub fn has_flag(args: &[String], name: &str) -> bool {
+    args.iter().any(|arg| arg == name)
+}
+
+/// Checks that the command line consists only of the given `options`, each followed by
+/// a value, and the given `flags`, so a mistyped option is not silently ignored.
+pub fn check_options(args: &[String], options: &[&str], flags: &[&str]) -> Result<()> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if options.contains(&arg.as_str()) {
+            args.next().ok_or_else(|| anyhow!("{arg} requires a value"))?;
+        } else if !flags.contains(&arg.as_str()) {
+            return Err(anyhow!("unknown option {arg}"));
+        }
+    }
+
+    Ok(())
+}
+
 // Additional common functions
 
 pub mod grid;
+pub mod line_pattern;
+pub mod matching;
+pub mod parallel;
+pub mod word_search;
 
 #[cfg(test)]
 mod tests {
@@ -39,4 +275,54 @@ mod tests {
     fn it_works() {
         start_day("00");
     }
+
+    #[test]
+    fn test_read_two_sections() {
+        let input = "47|53  \r\n97|13\r\n\r\n75,47\r\n";
+        let (rules, updates) = read_two_sections(input.as_bytes()).unwrap();
+        assert_eq!(rules[1], Line { number: 2, text: "97|13".to_string() });
+        assert_eq!(updates[0].number, 4);
+
+        let error = read_two_sections("47|53\n75,47\n".as_bytes()).unwrap_err();
+        assert_eq!(format!("{error:#}"), "line 3: missing second section, sections must be separated by a blank line");
+
+        let line = &updates[0];
+        assert_eq!(format!("{:#}", line.parse::<u32>().unwrap_err()), "line 4: cannot parse \"75,47\": invalid digit found in string");
+    }
+
+    #[test]
+    fn test_sections() {
+        fn parse_number(line: &str) -> Result<u32> {
+            Ok(line.parse()?)
+        }
+
+        let input = "\n1\n2\n\n\nskipped\nlines\n\n3\nx\n";
+        let mut sections = Sections::new(input.as_bytes());
+        assert_eq!(sections.expect_section("numbers").unwrap().transform(parse_number).unwrap(), [1, 2]);
+        let mut skipped = sections.next_section().unwrap().unwrap();
+        assert_eq!(skipped.next().unwrap().unwrap().number, 6);
+
+        let error = sections.expect_section("more numbers").unwrap().transform(parse_number).unwrap_err();
+        assert_eq!(error.downcast_ref::<InputError>().map(|e| e.line), Some(10));
+        let source = error.chain().nth(1).unwrap();
+        assert!(source.downcast_ref::<std::num::ParseIntError>().is_some());
+        assert_eq!(format!("{error:#}"), "line 10: invalid digit found in string");
+        assert!(sections.expect_end().is_ok());
+    }
+
+    #[test]
+    fn test_parse_option() {
+        let args = ["--rows", "7", "--time", "12"].map(String::from);
+        assert_eq!(parse_option::<usize>(&args, "--time").unwrap(), Some(12));
+        assert_eq!(parse_option::<usize>(&args, "--cols").unwrap(), None);
+        assert!(parse_option::<usize>(&args[..3], "--time").is_err());
+        assert!(parse_option::<usize>(&args, "--rows").is_ok());
+
+        assert!(check_options(&args, &["--rows", "--time"], &[]).is_ok());
+        let error = check_options(&args, &["--row", "--time"], &[]).unwrap_err();
+        assert_eq!(error.to_string(), "unknown option --rows");
+        let flags = ["--pushes", "--step", "3"].map(String::from);
+        assert!(check_options(&flags, &["--step"], &["--pushes"]).is_ok());
+        assert!(has_flag(&flags, "--pushes"));
+    }
 }
@@ -0,0 +1,119 @@
+use std::fmt::Display;
+use std::io::{self, BufRead};
+use std::marker::PhantomData;
+use std::str::FromStr;
+use anyhow::{anyhow, Result};
+use regex::{Captures, Regex};
+use crate::{InputError, Line};
+
+/// Builds a value from the named captures of a [`LinePattern`].
+pub trait FromCaptures: Sized {
+    fn from_captures(fields: &Fields) -> Result<Self>;
+}
+
+/// The named captures of a matched line with typed access.
+pub struct Fields<'a> {
+    captures: Captures<'a>,
+}
+
+impl Fields<'_> {
+    pub fn str(&self, name: &str) -> Result<&str> {
+        self.captures
+            .name(name)
+            .map(|m| m.as_str())
+            .ok_or_else(|| anyhow!("missing field {name}"))
+    }
+
+    pub fn get<T>(&self, name: &str) -> Result<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let value = self.str(name)?;
+        value
+            .parse()
+            .map_err(|e| anyhow!("field {name}: cannot parse {value:?}: {e}"))
+    }
+}
+
+/// A regular expression that a whole line has to match. The named groups are mapped
+/// onto `T` with [`FromCaptures`].
+///
+/// ```ignore
+/// let pattern = LinePattern::<Robot>::new(r"p=(?<x>-?\d+),(?<y>-?\d+) v=(?<vx>-?\d+),(?<vy>-?\d+)")?;
+/// let robots = pattern.read(reader)?;
+/// ```
+pub struct LinePattern<T> {
+    regex: Regex,
+    target: PhantomData<T>,
+}
+
+impl<T: FromCaptures> LinePattern<T> {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let regex = Regex::new(&format!("^(?:{pattern})$"))?;
+        Ok(Self { regex, target: PhantomData })
+    }
+
+    pub fn parse(&self, text: &str) -> Result<T> {
+        let captures = self.regex
+            .captures(text)
+            .ok_or_else(|| anyhow!("{text:?} does not match {}", self.regex))?;
+
+        T::from_captures(&Fields { captures })
+    }
+
+    /// Parses a line of the input, reporting failures with the line number.
+    pub fn parse_line(&self, line: &Line) -> Result<T> {
+        self.parse(&line.text)
+            .map_err(|e| InputError::from_error(line.number, e).into())
+    }
+
+    /// Parses all lines, e.g. the lines of a [`Section`](crate::Section).
+    pub fn parse_all(&self, lines: impl IntoIterator<Item = io::Result<Line>>) -> Result<Vec<T>> {
+        lines
+            .into_iter()
+            .map(|line| self.parse_line(&line?))
+            .collect()
+    }
+
+    /// Parses every non-blank line of the input.
+    pub fn read(&self, reader: impl BufRead) -> Result<Vec<T>> {
+        let lines = reader
+            .lines()
+            .enumerate()
+            .map(|(idx, text)| text.map(|text| Line { number: idx + 1, text: text.trim_end().to_string() }))
+            .filter(|line| !line.as_ref().is_ok_and(|line| line.text.is_empty()));
+
+        self.parse_all(lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Move {
+        name: String,
+        dx: i32,
+        dy: i32,
+    }
+
+    impl FromCaptures for Move {
+        fn from_captures(fields: &Fields) -> Result<Self> {
+            Ok(Move { name: fields.str("name")?.to_string(), dx: fields.get("dx")?, dy: fields.get("dy")? })
+        }
+    }
+
+    #[test]
+    fn test_line_pattern() {
+        let pattern = LinePattern::<Move>::new(r"(?<name>\w+): (?<dx>\S+),(?<dy>\S+)").unwrap();
+        let moves = pattern.read("up: 0,-1\r\n\r\nright: 1,0\n".as_bytes()).unwrap();
+        assert_eq!(moves[1], Move { name: "right".to_string(), dx: 1, dy: 0 });
+
+        let error = pattern.read("up: 0,-1\nleft: x,0\n".as_bytes()).unwrap_err();
+        assert_eq!(format!("{error:#}"), "line 2: field dx: cannot parse \"x\": invalid digit found in string");
+        let error = pattern.read("up: 0,-1 and more\n".as_bytes()).unwrap_err();
+        assert!(format!("{error:#}").starts_with("line 1: \"up: 0,-1 and more\" does not match"));
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::VecDeque;
+
+const ROOT: usize = 0;
+
+#[derive(Debug, Clone, Default)]
+struct Node {
+    children: Vec<(u8, usize)>,
+    pattern: Option<usize>,
+    depth: usize,
+}
+
+/// Byte trie over a set of patterns. Patterns are identified by their index in the
+/// input; a duplicated pattern keeps the index of its first occurrence.
+#[derive(Debug, Clone)]
+pub struct Trie {
+    nodes: Vec<Node>,
+    num_patterns: usize,
+}
+
+impl Trie {
+    pub fn new<I, P>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<[u8]>,
+    {
+        let mut trie = Trie { nodes: vec![Node::default()], num_patterns: 0 };
+        for pattern in patterns {
+            trie.insert(pattern.as_ref());
+        }
+
+        trie
+    }
+
+    fn insert(&mut self, pattern: &[u8]) {
+        let mut node = ROOT;
+        for byte in pattern {
+            node = match self.child(node, *byte) {
+                Some(child) => child,
+                None => {
+                    let child = self.nodes.len();
+                    let depth = self.nodes[node].depth + 1;
+                    self.nodes.push(Node { depth, ..Node::default() });
+                    self.nodes[node].children.push((*byte, child));
+                    child
+                }
+            };
+        }
+        if self.nodes[node].pattern.is_none() {
+            self.nodes[node].pattern = Some(self.num_patterns);
+        }
+        self.num_patterns += 1;
+    }
+
+    fn child(&self, node: usize, byte: u8) -> Option<usize> {
+        self.nodes[node]
+            .children
+            .iter()
+            .find(|(b, _)| *b == byte)
+            .map(|(_, child)| *child)
+    }
+
+    pub fn num_patterns(&self) -> usize {
+        self.num_patterns
+    }
+
+    pub fn contains(&self, pattern: impl AsRef<[u8]>) -> bool {
+        let mut node = ROOT;
+        for byte in pattern.as_ref() {
+            match self.child(node, *byte) {
+                Some(child) => node = child,
+                None => return false,
+            }
+        }
+
+        self.nodes[node].pattern.is_some()
+    }
+
+    /// Iterates over the lengths of all patterns that are a prefix of `text`, shortest first.
+    pub fn prefix_matches<'a>(&'a self, text: &'a [u8]) -> PrefixMatches<'a> {
+        PrefixMatches { trie: self, text, node: Some(ROOT), started: false }
+    }
+}
+
+pub struct PrefixMatches<'a> {
+    trie: &'a Trie,
+    text: &'a [u8],
+    node: Option<usize>,
+    started: bool,
+}
+
+impl Iterator for PrefixMatches<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if !self.started {
+            self.started = true;
+            if self.trie.nodes[ROOT].pattern.is_some() {
+                return Some(0);
+            }
+        }
+
+        loop {
+            let node = self.node?;
+            let depth = self.trie.nodes[node].depth;
+            self.node = self.text
+                .get(depth)
+                .and_then(|byte| self.trie.child(node, *byte));
+            if let Some(next) = self.node {
+                if self.trie.nodes[next].pattern.is_some() {
+                    return Some(depth + 1);
+                }
+            }
+        }
+    }
+}
+
+/// A pattern occurrence in a scanned text, `text[start..end]`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Match {
+    pub pattern: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Aho–Corasick automaton that finds all occurrences of several patterns in one pass,
+/// including overlapping ones. Empty patterns are never reported.
+#[derive(Debug, Clone)]
+pub struct AhoCorasick {
+    trie: Trie,
+    /// Node of the longest proper suffix that is also in the trie
+    fail: Vec<usize>,
+    /// Nearest node along the fail chain that ends a pattern
+    output: Vec<Option<usize>>,
+}
+
+impl AhoCorasick {
+    pub fn new<I, P>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<[u8]>,
+    {
+        let trie = Trie::new(patterns);
+        let mut fail = vec![ROOT; trie.nodes.len()];
+        let mut output = vec![None; trie.nodes.len()];
+        let mut queue = VecDeque::from([ROOT]);
+
+        // Breadth-first, so the fail links of shallower nodes are already known
+        while let Some(node) = queue.pop_front() {
+            for (byte, child) in &trie.nodes[node].children {
+                if node != ROOT {
+                    let mut state = fail[node];
+                    fail[*child] = loop {
+                        if let Some(next) = trie.child(state, *byte) {
+                            break next;
+                        }
+                        if state == ROOT {
+                            break ROOT;
+                        }
+                        state = fail[state];
+                    };
+                }
+                let suffix = fail[*child];
+                output[*child] = if suffix != ROOT && trie.nodes[suffix].pattern.is_some() {
+                    Some(suffix)
+                } else {
+                    output[suffix]
+                };
+                queue.push_back(*child);
+            }
+        }
+
+        AhoCorasick { trie, fail, output }
+    }
+
+    pub fn num_patterns(&self) -> usize {
+        self.trie.num_patterns()
+    }
+
+    fn step(&self, mut state: usize, byte: u8) -> usize {
+        loop {
+            if let Some(next) = self.trie.child(state, byte) {
+                return next;
+            }
+            if state == ROOT {
+                return ROOT;
+            }
+            state = self.fail[state];
+        }
+    }
+
+    /// Iterates over all matches in `text`, ordered by end position and, for the same
+    /// end, from the longest to the shortest pattern.
+    pub fn find_overlapping<'a>(&'a self, text: &'a [u8]) -> Matches<'a> {
+        Matches { automaton: self, text, pos: 0, state: ROOT, pending: None }
+    }
+}
+
+pub struct Matches<'a> {
+    automaton: &'a AhoCorasick,
+    text: &'a [u8],
+    pos: usize,
+    state: usize,
+    pending: Option<usize>,
+}
+
+impl Iterator for Matches<'_> {
+    type Item = Match;
+
+    fn next(&mut self) -> Option<Match> {
+        loop {
+            if let Some(node) = self.pending {
+                let nodes = &self.automaton.trie.nodes;
+                self.pending = self.automaton.output[node];
+                return Some(Match {
+                    pattern: nodes[node].pattern?,
+                    start: self.pos - nodes[node].depth,
+                    end: self.pos,
+                });
+            }
+
+            let byte = *self.text.get(self.pos)?;
+            self.state = self.automaton.step(self.state, byte);
+            self.pos += 1;
+            self.pending = if self.automaton.trie.nodes[self.state].pattern.is_some() {
+                Some(self.state)
+            } else {
+                self.automaton.output[self.state]
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_matches() {
+        let trie = Trie::new(["r", "wr", "b", "bwu", "br", "brw"]);
+        assert_eq!(trie.prefix_matches(b"brwrr").collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(trie.prefix_matches(b"ubwu").count(), 0);
+        assert!(trie.contains("bwu"));
+        assert!(!trie.contains("bw"));
+    }
+
+    #[test]
+    fn test_find_overlapping() {
+        let automaton = AhoCorasick::new(["he", "she", "his", "hers"]);
+        let matches = automaton
+            .find_overlapping(b"ushers")
+            .map(|m| (m.pattern, m.start, m.end))
+            .collect::<Vec<_>>();
+        assert_eq!(matches, [(1, 1, 4), (0, 2, 4), (3, 2, 6)]);
+
+        let automaton = AhoCorasick::new(["XMAS", "SAMX"]);
+        assert_eq!(automaton.find_overlapping(b"XMASAMXMAS").count(), 3);
+    }
+}
@@ -0,0 +1,54 @@
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Maps `f` over `items`, on a thread pool if the `parallel` feature is enabled.
+/// The results are always returned in the order of the input items.
+pub fn par_map<T, U, F>(items: &[T], f: F) -> Vec<U>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> U + Sync + Send,
+{
+    #[cfg(feature = "parallel")]
+    {
+        items.par_iter().map(f).collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        items.iter().map(f).collect()
+    }
+}
+
+/// Maps `f` over `items` (see [`par_map`]) and folds the results in input order,
+/// so the outcome does not depend on how the work was scheduled.
+pub fn par_map_reduce<T, U, A, F, R>(items: &[T], f: F, init: A, reduce: R) -> A
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> U + Sync + Send,
+    R: FnMut(A, U) -> A,
+{
+    par_map(items, f).into_iter().fold(init, reduce)
+}
+
+/// Counts the items that satisfy `predicate`.
+pub fn par_count<T, F>(items: &[T], predicate: F) -> usize
+where
+    T: Sync,
+    F: Fn(&T) -> bool + Sync + Send,
+{
+    par_map_reduce(items, predicate, 0, |count, ok| count + ok as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_par_map_keeps_order() {
+        let items = (0..1000).collect::<Vec<u64>>();
+        let squares = par_map(&items, |x| x * x);
+        assert_eq!(squares, items.iter().map(|x| x * x).collect::<Vec<_>>());
+        assert_eq!(par_count(&items, |x| x % 3 == 0), 334);
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::HashSet;
+use anyhow::{anyhow, Result};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+use crate::grid::{Grid, Position};
+
+/// The eight directions a word can be read in.
+#[derive(Debug, EnumIter, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Heading {
+    N,
+    S,
+    E,
+    W,
+    Ne,
+    Nw,
+    Se,
+    Sw,
+}
+
+impl Heading {
+    /// Row and column step of the heading.
+    pub fn delta(&self) -> (i32, i32) {
+        match self {
+            Heading::N => (-1, 0),
+            Heading::S => (1, 0),
+            Heading::E => (0, 1),
+            Heading::W => (0, -1),
+            Heading::Ne => (-1, 1),
+            Heading::Nw => (-1, -1),
+            Heading::Se => (1, 1),
+            Heading::Sw => (1, -1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct WordMatch {
+    pub start: Position,
+    pub heading: Heading,
+    pub len: usize,
+}
+
+impl WordMatch {
+    pub fn positions(&self) -> impl Iterator<Item = Position> + '_ {
+        let (dr, dc) = self.heading.delta();
+        (0..self.len as i32).map(move |i| {
+            Position::new(self.start.row() + i * dr, self.start.col() + i * dc)
+        })
+    }
+}
+
+/// A small 2D pattern of characters, parsed from an ASCII template in which `.`
+/// matches any character.
+///
+/// ```text
+/// M.S
+/// .A.
+/// M.S
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Stencil {
+    num_rows: i32,
+    num_cols: i32,
+    /// Row and column offsets of the non-wildcard cells, in row-major order
+    cells: Vec<(i32, i32, char)>,
+}
+
+impl Stencil {
+    pub const WILDCARD: char = '.';
+
+    pub fn parse(template: &str) -> Result<Stencil> {
+        let lines = template.lines().collect::<Vec<_>>();
+        let num_rows = lines.len() as i32;
+        let num_cols = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0) as i32;
+        let cells = lines
+            .iter()
+            .enumerate()
+            .flat_map(|(row, line)| {
+                line.chars()
+                    .enumerate()
+                    .filter(|(_, ch)| *ch != Self::WILDCARD)
+                    .map(move |(col, ch)| (row as i32, col as i32, ch))
+            })
+            .collect::<Vec<_>>();
+
+        if cells.is_empty() {
+            return Err(anyhow!("stencil template has no characters to match"));
+        }
+
+        Ok(Stencil { num_rows, num_cols, cells })
+    }
+
+    /// Rotates the stencil clockwise by 90 degrees.
+    pub fn rotate(&self) -> Stencil {
+        let mut cells = self.cells
+            .iter()
+            .map(|(row, col, ch)| (*col, self.num_rows - 1 - row, *ch))
+            .collect::<Vec<_>>();
+        cells.sort();
+
+        Stencil { num_rows: self.num_cols, num_cols: self.num_rows, cells }
+    }
+
+    /// The distinct stencils among the four rotations, starting with this one.
+    pub fn rotations(&self) -> Vec<Stencil> {
+        let mut ret: Vec<Stencil> = vec![self.clone()];
+        for _ in 1..4 {
+            let next = ret.last().unwrap().rotate();
+            if !ret.contains(&next) {
+                ret.push(next);
+            }
+        }
+
+        ret
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct StencilMatch {
+    pub top_left: Position,
+    /// Positions of the matched non-wildcard cells
+    pub positions: Vec<Position>,
+}
+
+impl Grid<char> {
+    /// Finds all occurrences of `word` in the eight directions. Palindromes are
+    /// found twice, once in each reading direction.
+    pub fn find_word(&self, word: &str) -> Vec<WordMatch> {
+        let chars = word.chars().collect::<Vec<_>>();
+        let mut ret = Vec::new();
+        if chars.is_empty() {
+            return ret;
+        }
+
+        for row in 0..self.num_rows {
+            for col in 0..self.num_cols {
+                let start = Position::new(row, col);
+                if self.value_at(&start) != Some(&chars[0]) {
+                    continue;
+                }
+                for heading in Heading::iter() {
+                    let (dr, dc) = heading.delta();
+                    let is_match = chars.iter().enumerate().skip(1).all(|(i, ch)| {
+                        let pos = Position::new(row + i as i32 * dr, col + i as i32 * dc);
+                        self.value_at(&pos) == Some(ch)
+                    });
+                    if is_match {
+                        ret.push(WordMatch { start: start.clone(), heading, len: chars.len() });
+                    }
+                }
+            }
+        }
+
+        ret
+    }
+
+    pub fn count_word(&self, word: &str) -> usize {
+        self.find_word(word).len()
+    }
+
+    /// Finds all placements of `stencil` that lie completely inside the grid.
+    pub fn find_stencil(&self, stencil: &Stencil) -> Vec<StencilMatch> {
+        let mut ret = Vec::new();
+
+        for row in 0..=self.num_rows - stencil.num_rows {
+            for col in 0..=self.num_cols - stencil.num_cols {
+                let is_match = stencil.cells.iter().all(|(dr, dc, ch)| {
+                    self.value_at(&Position::new(row + dr, col + dc)) == Some(ch)
+                });
+                if is_match {
+                    let positions = stencil.cells
+                        .iter()
+                        .map(|(dr, dc, _)| Position::new(row + dr, col + dc))
+                        .collect();
+                    ret.push(StencilMatch { top_left: Position::new(row, col), positions });
+                }
+            }
+        }
+
+        ret
+    }
+
+    /// Renders the grid with all cells that are not in `positions` replaced by `.`.
+    pub fn highlight<'a>(&self, positions: impl IntoIterator<Item = &'a Position>) -> String {
+        let highlighted = positions.into_iter().collect::<HashSet<_>>();
+        let mut ret = String::new();
+
+        for (row, cells) in self.cells.iter().enumerate() {
+            for (col, ch) in cells.iter().enumerate() {
+                let pos = Position::new(row as i32, col as i32);
+                ret.push(if highlighted.contains(&pos) { *ch } else { '.' });
+            }
+            ret.push('\n');
+        }
+
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_grid(lines: &[&str]) -> Grid<char> {
+        let cells = lines
+            .iter()
+            .map(|line| line.chars().collect())
+            .collect();
+        Grid::new(lines.len() as i32, lines[0].len() as i32, cells)
+    }
+
+    #[test]
+    fn test_find_word() {
+        let grid = make_grid(&["XMAS", "MM..", "A.A.", "S..S"]);
+        let matches = grid.find_word("XMAS");
+        let headings = matches.iter().map(|m| m.heading).collect::<HashSet<_>>();
+        assert_eq!(headings, HashSet::from([Heading::E, Heading::S, Heading::Se]));
+
+        let diagonal = matches.iter().find(|m| m.heading == Heading::Se).unwrap();
+        let positions = diagonal.positions().collect::<Vec<_>>();
+        assert_eq!(grid.highlight(&positions), "X...\n.M..\n..A.\n...S\n");
+    }
+
+    #[test]
+    fn test_find_stencil() {
+        let grid = make_grid(&["M.S.", ".A..", "MSSS", "..A.", ".M.M"]);
+        let stencil = Stencil::parse("M.S\n.A.\nM.S").unwrap();
+        assert_eq!(stencil.rotations().len(), 4);
+
+        let matches = stencil
+            .rotations()
+            .iter()
+            .flat_map(|s| grid.find_stencil(s))
+            .map(|m| m.top_left)
+            .collect::<Vec<_>>();
+        assert_eq!(matches, [Position::new(0, 0), Position::new(2, 1)]);
+    }
+}